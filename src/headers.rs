@@ -1,31 +1,61 @@
 /// HTTP content types
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub enum ContentType {
+pub enum ContentType<'a> {
     TextPlain,
+    TextCsv,
     ApplicationJson,
     ApplicationCbor,
     ApplicationOctetStream,
+    ImagePng,
+    ImageJpeg,
+    /// Any content type not known by this crate
+    Other(&'a str),
 }
 
-impl<'a> From<&'a [u8]> for ContentType {
-    fn from(from: &'a [u8]) -> ContentType {
-        match from {
-            b"application/json" => ContentType::ApplicationJson,
-            b"application/cbor" => ContentType::ApplicationCbor,
-            b"text/plain" => ContentType::TextPlain,
-            _ => ContentType::ApplicationOctetStream,
+impl<'a> From<&'a [u8]> for ContentType<'a> {
+    fn from(from: &'a [u8]) -> ContentType<'a> {
+        match core::str::from_utf8(from) {
+            Ok(value) => ContentType::from_str(value),
+            Err(_) => ContentType::ApplicationOctetStream,
         }
     }
 }
 
-impl ContentType {
-    pub fn as_str(&self) -> &str {
+impl<'a> ContentType<'a> {
+    /// Map a media type, such as the value of a `Content-Type` header, to a content type.
+    ///
+    /// The comparison is case-insensitive. Unknown media types are returned as [`ContentType::Other`].
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(value: &'a str) -> ContentType<'a> {
+        let value = value.trim();
+        Self::from_known(value).unwrap_or(ContentType::Other(value))
+    }
+
+    pub(crate) fn from_known(value: &str) -> Option<ContentType<'static>> {
+        [
+            ContentType::TextPlain,
+            ContentType::TextCsv,
+            ContentType::ApplicationJson,
+            ContentType::ApplicationCbor,
+            ContentType::ApplicationOctetStream,
+            ContentType::ImagePng,
+            ContentType::ImageJpeg,
+        ]
+        .into_iter()
+        .find(|content_type| content_type.as_str().eq_ignore_ascii_case(value))
+    }
+
+    pub fn as_str(&self) -> &'a str {
         match self {
             ContentType::TextPlain => "text/plain",
+            ContentType::TextCsv => "text/csv",
             ContentType::ApplicationJson => "application/json",
             ContentType::ApplicationCbor => "application/cbor",
             ContentType::ApplicationOctetStream => "application/octet-stream",
+            ContentType::ImagePng => "image/png",
+            ContentType::ImageJpeg => "image/jpeg",
+            ContentType::Other(value) => value,
         }
     }
 }
@@ -66,11 +96,23 @@ impl TransferEncoding {
 }
 
 /// Keep-alive header
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct KeepAlive {
-    timeout: Option<u8>,
-    max: Option<u8>,
+    timeout: Option<u32>,
+    max: Option<u32>,
+}
+
+impl KeepAlive {
+    /// Get the number of seconds that the server keeps an idle connection open, if sent.
+    pub fn timeout(&self) -> Option<u32> {
+        self.timeout
+    }
+
+    /// Get the maximum number of requests that the server accepts on the connection, if sent.
+    pub fn max(&self) -> Option<u32> {
+        self.max
+    }
 }
 
 impl<'a> TryFrom<&'a [u8]> for KeepAlive {
@@ -94,3 +136,382 @@ impl<'a> TryFrom<&'a [u8]> for KeepAlive {
         Ok(keep_alive)
     }
 }
+
+/// Retry-After header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RetryAfter {
+    /// The number of seconds to wait before retrying
+    Delay(u32),
+    /// The time after which to retry, in seconds since the Unix epoch
+    Date(u64),
+}
+
+impl<'a> TryFrom<&'a [u8]> for RetryAfter {
+    type Error = ();
+
+    fn try_from(value: &'a [u8]) -> Result<Self, Self::Error> {
+        let value = core::str::from_utf8(value).map_err(|_| ())?.trim();
+        if value.bytes().all(|b| b.is_ascii_digit()) {
+            return value.parse().map(RetryAfter::Delay).map_err(|_| ());
+        }
+        parse_http_date(value).map(RetryAfter::Date).ok_or(())
+    }
+}
+
+/// A date formatted as an HTTP-date in the IMF-fixdate format, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct HttpDate([u8; 29]);
+
+impl HttpDate {
+    /// Format a time in seconds since the Unix epoch.
+    ///
+    /// Years after 9999 cannot be represented and are formatted as 9999.
+    pub fn from_unix(timestamp: u64) -> Self {
+        const WEEKDAYS: [&[u8; 3]; 7] = [b"Thu", b"Fri", b"Sat", b"Sun", b"Mon", b"Tue", b"Wed"];
+        const MONTHS: [&[u8; 3]; 12] = [
+            b"Jan", b"Feb", b"Mar", b"Apr", b"May", b"Jun", b"Jul", b"Aug", b"Sep", b"Oct", b"Nov", b"Dec",
+        ];
+
+        let days = timestamp / 86400;
+        let seconds = timestamp % 86400;
+
+        // The civil date of the days since the epoch, the inverse of the conversion in `parse_http_date`
+        let (era, day_of_era) = ((days + 719468) / 146097, (days + 719468) % 146097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let (year, month) = if month < 10 {
+            (era * 400 + year_of_era, month + 3)
+        } else {
+            (era * 400 + year_of_era + 1, month - 9)
+        };
+
+        let mut date = *b"Thu, 01 Jan 1970 00:00:00 GMT";
+        date[..3].copy_from_slice(WEEKDAYS[(days % 7) as usize]);
+        write_digits(&mut date[5..7], day);
+        date[8..11].copy_from_slice(MONTHS[month as usize - 1]);
+        write_digits(&mut date[12..16], year.min(9999));
+        write_digits(&mut date[17..19], seconds / 3600);
+        write_digits(&mut date[20..22], seconds / 60 % 60);
+        write_digits(&mut date[23..25], seconds % 60);
+        Self(date)
+    }
+
+    /// Get the formatted date.
+    pub fn as_str(&self) -> &str {
+        // The date only consists of ASCII characters
+        core::str::from_utf8(&self.0).unwrap_or_default()
+    }
+}
+
+impl core::fmt::Debug for HttpDate {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for HttpDate {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "{=str}", self.as_str())
+    }
+}
+
+/// Write `value` as zero-padded decimal digits filling `buf`.
+fn write_digits(buf: &mut [u8], mut value: u64) {
+    for digit in buf.iter_mut().rev() {
+        *digit = b'0' + (value % 10) as u8;
+        value /= 10;
+    }
+}
+
+/// A challenge of a WWW-Authenticate header, such as `Basic realm="device"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AuthChallenge<'a> {
+    scheme: &'a str,
+    params: &'a str,
+}
+
+impl<'a> AuthChallenge<'a> {
+    /// Get the authentication scheme, such as `Basic`, `Bearer` or `Digest`.
+    ///
+    /// Schemes are case-insensitive, so compare them with [`str::eq_ignore_ascii_case`].
+    pub fn scheme(&self) -> &'a str {
+        self.scheme
+    }
+
+    /// Get the token of a challenge that has a single token instead of parameters.
+    pub fn token68(&self) -> Option<&'a str> {
+        let token = self.params.trim_end_matches('=');
+        let is_token68 = !token.is_empty()
+            && token
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-._~+/".contains(&b));
+        is_token68.then_some(self.params)
+    }
+
+    /// Get the parameters of the challenge, such as `realm` and `nonce`.
+    ///
+    /// The values are returned without the surrounding quotes, but escapes within quoted values are kept.
+    pub fn params(&self) -> AuthParams<'a> {
+        AuthParams(if self.token68().is_some() { "" } else { self.params })
+    }
+
+    /// Get the value of the parameter with the given name.
+    pub fn param(&self, name: &str) -> Option<&'a str> {
+        self.params()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+}
+
+/// Iterator over the challenges of a WWW-Authenticate header
+#[derive(Debug, Clone)]
+pub struct AuthChallenges<'a>(&'a str);
+
+impl<'a> AuthChallenges<'a> {
+    /// Iterate the challenges of a WWW-Authenticate header value.
+    pub fn new(value: &'a str) -> Self {
+        Self(value)
+    }
+}
+
+impl<'a> Iterator for AuthChallenges<'a> {
+    type Item = AuthChallenge<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let list = self.0;
+        let start = list.len() - list.trim_start_matches([',', ' ', '\t']).len();
+        if start == list.len() {
+            self.0 = "";
+            return None;
+        }
+
+        let first_end = list_item_end(list, start);
+        let first = &list[start..first_end];
+        let (scheme, params_start) = match first.find([' ', '\t']) {
+            Some(index) => (&first[..index], start + index + 1),
+            None => (first, first_end),
+        };
+
+        // The following list elements are parameters of this challenge until the next scheme
+        let mut end = first_end;
+        while end < list.len() {
+            let next_end = list_item_end(list, end + 1);
+            let item = list[end + 1..next_end].trim();
+            let is_param = item
+                .split_once('=')
+                .is_some_and(|(name, _)| !name.trim_end().is_empty() && !name.trim_end().contains([' ', '\t']));
+            if !item.is_empty() && !is_param {
+                break;
+            }
+            end = next_end;
+        }
+
+        self.0 = list.get(end + 1..).unwrap_or_default();
+        Some(AuthChallenge {
+            scheme,
+            params: list[params_start..end].trim(),
+        })
+    }
+}
+
+/// Iterator over the `name=value` parameters of an [`AuthChallenge`]
+#[derive(Debug, Clone)]
+pub struct AuthParams<'a>(&'a str);
+
+impl<'a> Iterator for AuthParams<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.0.is_empty() {
+            let end = list_item_end(self.0, 0);
+            let item = &self.0[..end];
+            self.0 = self.0.get(end + 1..).unwrap_or_default();
+
+            if let Some((name, value)) = item.split_once('=') {
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+                return Some((name.trim(), value));
+            }
+        }
+        None
+    }
+}
+
+/// Find the end of the comma-separated list element starting at `start`, skipping commas inside quoted strings.
+fn list_item_end(list: &str, start: usize) -> usize {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, byte) in list.bytes().enumerate().skip(start) {
+        match byte {
+            _ if escaped => escaped = false,
+            b'\\' if quoted => escaped = true,
+            b'"' => quoted = !quoted,
+            b',' if !quoted => return index,
+            _ => {}
+        }
+    }
+    list.len()
+}
+
+/// Parse an HTTP date in the preferred IMF-fixdate format, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`,
+/// into seconds since the Unix epoch.
+fn parse_http_date(value: &str) -> Option<u64> {
+    let mut parts = value.split_ascii_whitespace();
+    let (_weekday, day, month, year, time, zone) = (
+        parts.next()?,
+        parts.next()?,
+        parts.next()?,
+        parts.next()?,
+        parts.next()?,
+        parts.next()?,
+    );
+    if zone != "GMT" || parts.next().is_some() {
+        return None;
+    }
+
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let month = MONTHS.iter().position(|m| *m == month)? as u64 + 1;
+    let day: u64 = day.parse().ok().filter(|day| (1..=31).contains(day))?;
+    let year: u64 = year.parse().ok().filter(|year| *year >= 1970)?;
+
+    let mut time = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if time.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    // Days since the epoch of the civil date, counting years from March so that leap days come last
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let (era, year_of_era) = (year / 400, year % 400);
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_type_round_trip() {
+        for content_type in [
+            ContentType::TextPlain,
+            ContentType::TextCsv,
+            ContentType::ApplicationJson,
+            ContentType::ApplicationCbor,
+            ContentType::ApplicationOctetStream,
+            ContentType::ImagePng,
+            ContentType::ImageJpeg,
+        ] {
+            assert_eq!(content_type, ContentType::from_str(content_type.as_str()));
+        }
+    }
+
+    #[test]
+    fn keep_alive_params() {
+        let keep_alive = KeepAlive::try_from(b"timeout=5, max=1000".as_slice()).unwrap();
+        assert_eq!(Some(5), keep_alive.timeout());
+        assert_eq!(Some(1000), keep_alive.max());
+
+        let keep_alive = KeepAlive::try_from(b"Timeout=soon".as_slice()).unwrap();
+        assert_eq!(None, keep_alive.timeout());
+        assert_eq!(None, keep_alive.max());
+    }
+
+    #[test]
+    fn content_type_from_str() {
+        assert_eq!(ContentType::ImagePng, ContentType::from_str("IMAGE/PNG"));
+        assert_eq!(ContentType::TextCsv, ContentType::from(b"text/csv".as_slice()));
+        assert_eq!(
+            ContentType::Other("application/x-custom"),
+            ContentType::from_str("application/x-custom")
+        );
+        assert_eq!(
+            "application/x-custom",
+            ContentType::Other("application/x-custom").as_str()
+        );
+    }
+
+    #[test]
+    fn retry_after_delay() {
+        assert_eq!(Ok(RetryAfter::Delay(120)), RetryAfter::try_from(b"120".as_slice()));
+        assert_eq!(Err(()), RetryAfter::try_from(b"-1".as_slice()));
+    }
+
+    #[test]
+    fn retry_after_date() {
+        assert_eq!(
+            Ok(RetryAfter::Date(784111777)),
+            RetryAfter::try_from(b"Sun, 06 Nov 1994 08:49:37 GMT".as_slice())
+        );
+        assert_eq!(
+            Ok(RetryAfter::Date(951782400)),
+            RetryAfter::try_from(b"Tue, 29 Feb 2000 00:00:00 GMT".as_slice())
+        );
+        assert_eq!(
+            Err(()),
+            RetryAfter::try_from(b"Sunday, 06-Nov-94 08:49:37 GMT".as_slice())
+        );
+    }
+
+    #[test]
+    fn auth_challenges() {
+        let mut challenges =
+            AuthChallenges::new(r#"Basic realm="a, b", Bearer realm="api", error="invalid_token", Negotiate abc+/=="#);
+
+        let basic = challenges.next().unwrap();
+        assert_eq!("Basic", basic.scheme());
+        assert_eq!(Some("a, b"), basic.param("REALM"));
+
+        let bearer = challenges.next().unwrap();
+        assert_eq!("Bearer", bearer.scheme());
+        assert_eq!(Some("api"), bearer.param("realm"));
+        assert_eq!(Some("invalid_token"), bearer.param("error"));
+        assert_eq!(None, bearer.token68());
+
+        let negotiate = challenges.next().unwrap();
+        assert_eq!("Negotiate", negotiate.scheme());
+        assert_eq!(Some("abc+/=="), negotiate.token68());
+        assert_eq!(None, negotiate.params().next());
+
+        assert_eq!(None, challenges.next());
+    }
+
+    #[test]
+    fn auth_challenge_without_params() {
+        let mut challenges = AuthChallenges::new("Basic, Digest nonce=\"xyz\", qop=auth");
+
+        assert_eq!("Basic", challenges.next().unwrap().scheme());
+        let digest = challenges.next().unwrap();
+        assert_eq!("Digest", digest.scheme());
+        assert_eq!(Some("xyz"), digest.param("nonce"));
+        assert_eq!(Some("auth"), digest.param("qop"));
+        assert_eq!(None, challenges.next());
+    }
+
+    #[test]
+    fn http_date_round_trip() {
+        assert_eq!("Thu, 01 Jan 1970 00:00:00 GMT", HttpDate::from_unix(0).as_str());
+        assert_eq!("Tue, 29 Feb 2000 00:00:00 GMT", HttpDate::from_unix(951782400).as_str());
+        for timestamp in [784111777, 951782400, 1709251199, 4102444800] {
+            let date = HttpDate::from_unix(timestamp);
+            assert_eq!(Some(timestamp), parse_http_date(date.as_str()));
+        }
+    }
+}
@@ -9,9 +9,15 @@ mod fmt;
 
 pub mod client;
 pub mod headers;
+pub mod percent;
 mod reader;
 pub mod request;
 pub mod response;
+pub mod signing;
+#[cfg(feature = "std")]
+pub mod std_net;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 /// Errors that can be returned by this library.
 #[derive(Debug)]
@@ -19,10 +25,18 @@ pub mod response;
 pub enum Error {
     /// An error with DNS (it's always DNS)
     Dns,
-    /// An error with the underlying network
+    /// An error with the underlying network, other than a refused or reset connection
     Network(embedded_io::ErrorKind),
+    /// The connection was refused by the server
+    ConnectionRefused,
+    /// The connection was reset by the server
+    ConnectionReset,
     /// An error encoding or decoding data
     Codec,
+    /// The status line of the response is not of the form `HTTP/x.y SSS reason`
+    MalformedStatusLine,
+    /// The response is malformed or ambiguous
+    InvalidResponse,
     /// An error parsing the URL
     InvalidUrl(nourl::Error),
     /// Tls Error
@@ -30,18 +44,35 @@ pub enum Error {
     Tls(embedded_tls::TlsError),
     /// The provided buffer is too small
     BufferTooSmall,
+    /// The response header section is larger than the buffer or the configured maximum
+    HeadersTooLarge {
+        /// The size of the header section, or a lower bound if its end was not received
+        needed: usize,
+    },
+    /// The response body exceeds the configured maximum body length
+    BodyTooLarge,
     /// The request is already sent
     AlreadySent,
+    /// The request cannot be pipelined as its method is not idempotent
+    NotIdempotent,
     /// An invalid number of bytes were written to request body
     IncorrectBodyWritten,
+    /// A streamed request body ended before or continued after its declared length
+    BodyLengthMismatch,
     /// The underlying connection was closed while being used
     ConnectionAborted,
+    /// The body ended before the requested number of bytes could be read
+    UnexpectedEof,
+    /// The digest of the body does not match the digest sent by the server
+    IntegrityCheckFailed,
 }
 
 impl embedded_io::Error for Error {
     fn kind(&self) -> embedded_io::ErrorKind {
         match self {
             Error::Network(kind) => *kind,
+            Error::ConnectionRefused => embedded_io::ErrorKind::ConnectionRefused,
+            Error::ConnectionReset => embedded_io::ErrorKind::ConnectionReset,
             Error::ConnectionAborted => embedded_io::ErrorKind::ConnectionAborted,
             _ => embedded_io::ErrorKind::Other,
         }
@@ -50,7 +81,11 @@ impl embedded_io::Error for Error {
 
 impl From<embedded_io::ErrorKind> for Error {
     fn from(e: embedded_io::ErrorKind) -> Error {
-        Error::Network(e)
+        match e {
+            embedded_io::ErrorKind::ConnectionRefused => Error::ConnectionRefused,
+            embedded_io::ErrorKind::ConnectionReset => Error::ConnectionReset,
+            kind => Error::Network(kind),
+        }
     }
 }
 
@@ -58,7 +93,7 @@ impl<E: embedded_io::Error> From<ReadExactError<E>> for Error {
     fn from(value: ReadExactError<E>) -> Self {
         match value {
             ReadExactError::UnexpectedEof => Error::ConnectionAborted,
-            ReadExactError::Other(e) => Error::Network(e.kind()),
+            ReadExactError::Other(e) => e.kind().into(),
         }
     }
 }
@@ -97,26 +132,43 @@ pub trait TryBufRead: embedded_io_async::Read {
     fn try_consume(&mut self, _amt: usize) {}
 }
 
+impl<T> TryBufRead for buffered_io::asynch::BufferedRead<'_, T>
+where
+    T: embedded_io_async::Read,
+{
+    async fn try_fill_buf(&mut self) -> Option<Result<&[u8], Self::Error>> {
+        use embedded_io_async::BufRead;
+        Some(self.fill_buf().await)
+    }
+
+    fn try_consume(&mut self, amt: usize) {
+        use embedded_io_async::BufRead;
+        self.consume(amt)
+    }
+}
+
 impl<C> TryBufRead for crate::client::HttpConnection<'_, C>
 where
     C: embedded_io_async::Read + embedded_io_async::Write,
 {
     async fn try_fill_buf(&mut self) -> Option<Result<&[u8], Self::Error>> {
-        // embedded-tls has its own internal buffer, let's prefer that if we can
-        #[cfg(feature = "embedded-tls")]
-        if let Self::Tls(ref mut tls) = self {
-            use embedded_io_async::{BufRead, Error};
-            return Some(tls.fill_buf().await.map_err(|e| e.kind()));
+        use embedded_io_async::{BufRead, Error};
+        match self {
+            Self::PlainBufferedRw(conn) => Some(conn.fill_buf().await.map_err(|e| e.kind())),
+            // embedded-tls has its own internal buffer, let's prefer that if we can
+            #[cfg(feature = "embedded-tls")]
+            Self::Tls(tls) => Some(tls.fill_buf().await.map_err(|e| e.kind())),
+            _ => None,
         }
-
-        None
     }
 
     fn try_consume(&mut self, amt: usize) {
-        #[cfg(feature = "embedded-tls")]
-        if let Self::Tls(tls) = self {
-            use embedded_io_async::BufRead;
-            tls.consume(amt);
+        use embedded_io_async::BufRead;
+        match self {
+            Self::PlainBufferedRw(conn) => conn.consume(amt),
+            #[cfg(feature = "embedded-tls")]
+            Self::Tls(tls) => tls.consume(amt),
+            _ => {}
         }
     }
 }
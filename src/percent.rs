@@ -0,0 +1,80 @@
+//! Percent-encoding as specified by RFC 3986.
+
+use crate::Error;
+
+const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Check whether the byte at `index` of a request target must be percent-encoded.
+///
+/// Unreserved characters, sub-delimiters, `:`, `@`, `/` and `?` are kept as is,
+/// and so are `%` characters that start an already encoded `%XX` sequence.
+pub(crate) fn needs_encoding(target: &[u8], index: usize) -> bool {
+    match target[index] {
+        b'%' => !target
+            .get(index + 1..index + 3)
+            .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)),
+        byte => !(byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/?".contains(&byte)),
+    }
+}
+
+/// Percent-encode a single byte.
+pub(crate) fn encode_byte(byte: u8) -> [u8; 3] {
+    [b'%', HEX[(byte >> 4) as usize], HEX[(byte & 0xF) as usize]]
+}
+
+/// Percent-decode `input` into `buf`, returning the decoded bytes.
+///
+/// `%` characters that do not start a valid `%XX` sequence are kept as is.
+/// Returns [`Error::BufferTooSmall`] if the decoded bytes do not fit in `buf`.
+pub fn decode<'b>(input: &[u8], buf: &'b mut [u8]) -> Result<&'b [u8], Error> {
+    let mut len = 0;
+    let mut index = 0;
+    while index < input.len() {
+        let byte = match input.get(index..index + 3) {
+            Some([b'%', hi, lo]) if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => {
+                index += 3;
+                (hex_value(*hi) << 4) | hex_value(*lo)
+            }
+            _ => {
+                index += 1;
+                input[index - 1]
+            }
+        };
+
+        *buf.get_mut(len).ok_or(Error::BufferTooSmall)? = byte;
+        len += 1;
+    }
+
+    Ok(&buf[..len])
+}
+
+fn hex_value(digit: u8) -> u8 {
+    match digit {
+        b'0'..=b'9' => digit - b'0',
+        b'a'..=b'f' => digit - b'a' + 10,
+        _ => digit - b'A' + 10,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_percent_encoded() {
+        let mut buf = [0; 16];
+        assert_eq!(b"my file.txt", decode(b"my%20file%2etxt", &mut buf).unwrap());
+    }
+
+    #[test]
+    fn decode_keeps_invalid_sequences() {
+        let mut buf = [0; 16];
+        assert_eq!(b"100% %zz %4", decode(b"100% %zz %4", &mut buf).unwrap());
+    }
+
+    #[test]
+    fn decode_into_small_buffer() {
+        let mut buf = [0; 4];
+        assert!(matches!(decode(b"a%20file", &mut buf), Err(Error::BufferTooSmall)));
+    }
+}
@@ -15,7 +15,7 @@ impl<'buf> ReadBuffer<'buf> {
 }
 
 impl ReadBuffer<'_> {
-    fn is_empty(&self) -> bool {
+    pub(crate) fn is_empty(&self) -> bool {
         self.loaded == 0
     }
 
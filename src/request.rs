@@ -1,38 +1,71 @@
 use crate::headers::ContentType;
+use crate::percent;
+use crate::signing::{RequestSigner, SigningWriter, MAX_SIGNATURE_LEN};
 /// Low level API for encoding requests and decoding responses.
 use crate::Error;
+use core::borrow::Borrow;
+use core::cell::{Cell, RefCell};
 use core::fmt::Write as _;
+use core::future::Future;
+use core::marker::PhantomData;
 use core::mem::size_of;
 use embedded_io::{Error as _, ErrorType};
-use embedded_io_async::Write;
+use embedded_io_async::{Read, Write};
 use heapless::String;
 
 /// A read only HTTP request type
+#[derive(Clone)]
 pub struct Request<'req, B>
 where
     B: RequestBody,
 {
     pub(crate) method: Method,
+    pub(crate) version: HttpVersion,
     pub(crate) base_path: Option<&'req str>,
     pub(crate) path: &'req str,
     pub(crate) auth: Option<Auth<'req>>,
     pub(crate) host: Option<&'req str>,
+    pub(crate) host_port: Option<u16>,
     pub(crate) body: Option<B>,
-    pub(crate) content_type: Option<ContentType>,
+    pub(crate) content_type: Option<ContentType<'req>>,
+    pub(crate) accept: Option<ContentType<'req>>,
+    pub(crate) accept_language: Option<&'req str>,
     pub(crate) extra_headers: Option<&'req [(&'req str, &'req str)]>,
+    pub(crate) raw_headers: Option<&'req [u8]>,
+    pub(crate) user_agent: Option<&'req str>,
+    pub(crate) keep_alive: Option<bool>,
+    pub(crate) header_source: Option<&'req dyn HeaderSource>,
+    pub(crate) if_match: Option<&'req str>,
+    pub(crate) date: Option<&'req str>,
+    pub(crate) signer: Option<&'req dyn RequestSigner>,
+    pub(crate) charset: Option<&'req str>,
+    pub(crate) referer: Option<&'req str>,
 }
 
 impl Default for Request<'_, ()> {
     fn default() -> Self {
         Self {
             method: Method::GET,
+            version: HttpVersion::Http11,
             base_path: None,
             path: "/",
             auth: None,
             host: None,
+            host_port: None,
             body: None,
             content_type: None,
+            accept: None,
+            accept_language: None,
             extra_headers: None,
+            raw_headers: None,
+            user_agent: None,
+            keep_alive: None,
+            header_source: None,
+            if_match: None,
+            date: None,
+            signer: None,
+            charset: None,
+            referer: None,
         }
     }
 }
@@ -48,21 +81,147 @@ where
     fn headers(self, headers: &'req [(&'req str, &'req str)]) -> Self;
     /// Set the path of the HTTP request.
     fn path(self, path: &'req str) -> Self;
+    /// Set the HTTP version of the request.
+    ///
+    /// HTTP/1.0 requests still include the `Host` header, use [`RequestBuilder::no_host`] to omit it.
+    /// They are sent with `Connection: close` unless [`RequestBuilder::keep_alive`] is set, as HTTP/1.0
+    /// servers close the connection after each response by default. HTTP/1.0 has no chunked encoding,
+    /// so writing an HTTP/1.0 request with a body of unknown length fails with [`Error::Codec`].
+    fn version(self, version: HttpVersion) -> Self;
     /// Set the data to send in the HTTP request body.
     fn body<T: RequestBody>(self, body: T) -> Self::WithBody<T>;
     /// Set the host header.
     fn host(self, host: &'req str) -> Self;
+    /// Do not send the host header, including the one set from the URL by the client.
+    ///
+    /// A host header added with [`RequestBuilder::headers`] is still sent.
+    fn no_host(self) -> Self;
     /// Set the content type header for the request.
-    fn content_type(self, content_type: ContentType) -> Self;
+    fn content_type(self, content_type: ContentType<'req>) -> Self;
+    /// Set the content type header for the request with a `charset` parameter,
+    /// such as `text/plain; charset=utf-8`.
+    fn content_type_with_charset(self, content_type: ContentType<'req>, charset: &'req str) -> Self;
+    /// Set the accept header for the request.
+    fn accept(self, content_type: ContentType<'req>) -> Self;
+    /// Set the accept language header for the request.
+    fn accept_language(self, language: &'req str) -> Self;
     /// Set the basic authentication header for the request.
     fn basic_auth(self, username: &'req str, password: &'req str) -> Self;
+    /// Set a pre-built header block that is written verbatim after the request line.
+    ///
+    /// The block must consist of complete header lines, each terminated by `\r\n`,
+    /// and must not include the empty line that terminates the header section.
+    /// The raw headers replace all other headers set on the builder, including the `Host` header
+    /// and the headers set with [`RequestBuilder::headers`], and the caller is fully responsible for them.
+    /// Only the `Content-Length` or `Transfer-Encoding` header derived from the body is still written.
+    fn raw_headers(self, headers: &'req [u8]) -> Self;
+    /// Set the user agent header for the request.
+    fn user_agent(self, user_agent: &'req str) -> Self;
+    /// Set the connection header for the request to `keep-alive` or `close`.
+    ///
+    /// No connection header is sent if not set, which means keep-alive for HTTP/1.1.
+    /// A connection header set with [`RequestBuilder::headers`] takes precedence.
+    fn keep_alive(self, keep_alive: bool) -> Self;
+    /// Set optional headers on the request that are iterated when the request is written.
+    ///
+    /// This is useful when the headers are not available as a slice, see [`HeaderSource`].
+    /// The headers are written after the headers set with [`RequestBuilder::headers`].
+    fn headers_iter(self, headers: &'req dyn HeaderSource) -> Self;
+    /// Set the if-match header for the request, making it conditional on the current entity tag of the resource.
+    ///
+    /// The entity tag is sent as is, so it must include the quotes, as returned by [`Response::etag`](crate::response::Response::etag).
+    /// The server responds with `412 Precondition Failed` if the resource has changed.
+    fn if_match(self, etag: &'req str) -> Self;
+    /// Set the date header for the request.
+    ///
+    /// The date is sent verbatim, so it should be an HTTP-date such as `Sun, 06 Nov 1994 08:49:37 GMT`,
+    /// which can be formatted from a Unix timestamp using [`HttpDate`](crate::headers::HttpDate).
+    fn date(self, date: &'req str) -> Self;
+    /// Sign the request, sending the signature in the authorization header.
+    ///
+    /// See [`RequestSigner`] for the bytes that are signed. The signature replaces the header set with
+    /// [`RequestBuilder::basic_auth`], but an authorization header set with [`RequestBuilder::headers`] takes precedence.
+    fn signer(self, signer: &'req dyn RequestSigner) -> Self;
+    /// Set the referer header for the request to the url of the resource that the request originates from.
+    ///
+    /// A `Referer` header set with [`RequestBuilder::headers`] takes precedence.
+    fn referer(self, referer: &'req str) -> Self;
     /// Return an immutable request.
     fn build(self) -> Request<'req, B>;
 }
 
+/// Extra request headers that are iterated when the request is written, set with [`RequestBuilder::headers_iter`].
+///
+/// The headers are requested in order, starting from index 0, and are iterated twice while the
+/// request is written: once to find the headers derived from the builder that they override, and once to write them.
+/// Use [`HeaderIter`] to provide the headers from anything that implements [`IntoIterator`].
+pub trait HeaderSource {
+    /// Get the header at `index`, or `None` if there are no more headers.
+    fn header(&self, index: usize) -> Option<(&str, &str)>;
+}
+
+/// A [`HeaderSource`] backed by anything that can be iterated more than once,
+/// such as `&headers` for a `heapless::Vec<(&str, &str), N>` or an iterator that implements [`Clone`].
+///
+/// The iterator is advanced as the headers are requested in order, so each pass over the headers iterates them once.
+pub struct HeaderIter<'a, I> {
+    headers: I,
+    /// The iterator of the current pass and the index of the header that it yields next.
+    current: RefCell<(usize, I)>,
+    _marker: PhantomData<&'a str>,
+}
+
+impl<'a, I> HeaderIter<'a, I>
+where
+    I: Iterator + Clone,
+    I::Item: Borrow<(&'a str, &'a str)>,
+{
+    /// Create a header source from anything that can be converted into a cloneable iterator of `(name, value)` pairs.
+    pub fn new<T>(headers: T) -> Self
+    where
+        T: IntoIterator<IntoIter = I>,
+    {
+        let headers = headers.into_iter();
+        Self {
+            current: RefCell::new((0, headers.clone())),
+            headers,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, I> HeaderSource for HeaderIter<'a, I>
+where
+    I: Iterator + Clone,
+    I::Item: Borrow<(&'a str, &'a str)>,
+{
+    fn header(&self, index: usize) -> Option<(&str, &str)> {
+        let mut current = self.current.borrow_mut();
+        let (next, iter) = &mut *current;
+        // Start a new pass when an earlier header is requested
+        if index < *next {
+            *next = 0;
+            *iter = self.headers.clone();
+        }
+        while *next < index {
+            iter.next()?;
+            *next += 1;
+        }
+        let header = *iter.next()?.borrow();
+        *next += 1;
+        Some(header)
+    }
+}
+
 /// Request authentication scheme.
+#[derive(Clone, Copy)]
 pub enum Auth<'a> {
-    Basic { username: &'a str, password: &'a str },
+    Basic {
+        username: &'a str,
+        password: &'a str,
+    },
+    /// Basic authentication with the percent-encoded `username:password` userinfo of a url
+    Userinfo(&'a str),
 }
 
 impl<'req> Request<'req, ()> {
@@ -91,6 +250,11 @@ impl<'req> Request<'req, ()> {
         Self::new(Method::PUT, path)
     }
 
+    /// Create a new PATCH http request.
+    pub fn patch(path: &'req str) -> DefaultRequestBuilder<'req, ()> {
+        Self::new(Method::PATCH, path)
+    }
+
     /// Create a new DELETE http request.
     pub fn delete(path: &'req str) -> DefaultRequestBuilder<'req, ()> {
         Self::new(Method::DELETE, path)
@@ -100,6 +264,21 @@ impl<'req> Request<'req, ()> {
     pub fn head(path: &'req str) -> DefaultRequestBuilder<'req, ()> {
         Self::new(Method::HEAD, path)
     }
+
+    /// Create a new OPTIONS http request.
+    pub fn options(path: &'req str) -> DefaultRequestBuilder<'req, ()> {
+        Self::new(Method::OPTIONS, path)
+    }
+
+    /// Create a new CONNECT http request that asks the server to open a tunnel to `authority`.
+    ///
+    /// The request target of a CONNECT request is in authority-form, i.e. the host and port of the
+    /// tunnel target such as `example.com:443`, instead of a path: `CONNECT example.com:443 HTTP/1.1`.
+    /// A base path is never prepended to it. Use [`crate::response::Response::into_tunnel`] to get the
+    /// tunnel after a successful response.
+    pub fn connect(authority: &'req str) -> DefaultRequestBuilder<'req, ()> {
+        Self::new(Method::CONNECT, authority)
+    }
 }
 
 impl<'req, B> Request<'req, B>
@@ -111,66 +290,34 @@ where
     where
         C: Write,
     {
-        write_str(c, self.method.as_str()).await?;
-        write_str(c, " ").await?;
-        if let Some(base_path) = self.base_path {
-            write_str(c, base_path.trim_end_matches('/')).await?;
-            if !self.path.starts_with('/') {
-                write_str(c, "/").await?;
-            }
-        }
-        write_str(c, self.path).await?;
-        write_str(c, " HTTP/1.1\r\n").await?;
-
-        if let Some(auth) = &self.auth {
-            match auth {
-                Auth::Basic { username, password } => {
-                    use base64::engine::{general_purpose, Engine as _};
-
-                    let mut combined: String<128> = String::new();
-                    write!(combined, "{}:{}", username, password).map_err(|_| Error::Codec)?;
-                    let mut authz = [0; 256];
-                    let authz_len = general_purpose::STANDARD
-                        .encode_slice(combined.as_bytes(), &mut authz)
-                        .map_err(|_| Error::Codec)?;
-                    write_str(c, "Authorization: Basic ").await?;
-                    write_str(c, unsafe { core::str::from_utf8_unchecked(&authz[..authz_len]) }).await?;
-                    write_str(c, "\r\n").await?;
-                }
-            }
-        }
-        if let Some(host) = &self.host {
-            write_header(c, "Host", host).await?;
-        }
-        if let Some(content_type) = &self.content_type {
-            write_header(c, "Content-Type", content_type.as_str()).await?;
-        }
-        if let Some(body) = self.body.as_ref() {
-            if let Some(len) = body.len() {
-                let mut s: String<32> = String::new();
-                write!(s, "{}", len).map_err(|_| Error::Codec)?;
-                write_header(c, "Content-Length", s.as_str()).await?;
-            } else {
-                write_header(c, "Transfer-Encoding", "chunked").await?;
-            }
+        if self.version == HttpVersion::Http10 && self.body.as_ref().is_some_and(|body| body.len().is_none()) {
+            warn!("HTTP/1.0 does not support chunked request bodies");
+            return Err(Error::Codec);
         }
-        if let Some(extra_headers) = self.extra_headers {
-            for (header, value) in extra_headers.iter() {
-                write_header(c, header, value).await?;
-            }
+        let overridden = self.overridden_headers();
+        if let Some(signer) = self.signer {
+            signer.begin();
+            self.write_head(&mut SigningWriter { conn: c, signer }, overridden)
+                .await?;
+
+            let mut signature = [0; MAX_SIGNATURE_LEN];
+            let signature = signer.finish(&mut signature)?;
+            write_structured_header(c, overridden, "Authorization", signature).await?;
+        } else {
+            self.write_head(c, overridden).await?;
         }
         write_str(c, "\r\n").await?;
         trace!("Header written");
         if let Some(body) = self.body.as_ref() {
             match body.len() {
-                Some(0) => {
-                    // Empty body
-                }
                 Some(len) => {
                     trace!("Writing not-chunked body");
                     let mut writer = FixedBodyWriter(c, 0);
                     body.write(&mut writer).await.map_err(to_errorkind)?;
 
+                    if let Some(e) = body.take_error() {
+                        return Err(e);
+                    }
                     if writer.1 != len {
                         return Err(Error::IncorrectBodyWritten);
                     }
@@ -180,6 +327,10 @@ where
                     let mut writer = ChunkedBodyWriter(c, 0);
                     body.write(&mut writer).await?;
 
+                    // A failed body is not terminated, so that it is not mistaken for a complete one
+                    if let Some(e) = body.take_error() {
+                        return Err(e);
+                    }
                     write_str(c, "0\r\n\r\n").await?;
                 }
             }
@@ -188,12 +339,215 @@ where
         c.flush().await.map_err(|e| e.kind())?;
         Ok(())
     }
+
+    /// Write the request line and all headers except the authorization header of a signed request
+    async fn write_head<C: Write>(&self, c: &mut C, overridden: u16) -> Result<(), Error> {
+        write_str(c, self.method.as_str()).await?;
+        write_str(c, " ").await?;
+        match self.base_path {
+            // The target of a CONNECT request is an authority, which is never relative to a base path
+            _ if self.method == Method::CONNECT => write_authority(c, self.path).await?,
+            // An empty path refers to the base path itself
+            Some(base_path) if self.path.is_empty() => {
+                write_path(c, if base_path.is_empty() { "/" } else { base_path }).await?;
+            }
+            // Exactly one slash separates the base path and the path
+            Some(base_path) => {
+                write_path(c, base_path.trim_end_matches('/')).await?;
+                write_str(c, "/").await?;
+                write_path(c, self.path.trim_start_matches('/')).await?;
+            }
+            None => write_path(c, self.path).await?,
+        }
+        write_str(c, " ").await?;
+        write_str(c, self.version.as_str()).await?;
+        write_str(c, "\r\n").await?;
+
+        if let Some(raw_headers) = self.raw_headers {
+            c.write_all(raw_headers).await.map_err(to_errorkind)?;
+        } else {
+            self.write_structured_headers(c, overridden).await?;
+        }
+        if let Some(body) = self.body.as_ref() {
+            if let Some(len) = body.len() {
+                let mut s: String<32> = String::new();
+                write!(s, "{}", len).map_err(|_| Error::Codec)?;
+                write_header(c, "Content-Length", s.as_str()).await?;
+            } else {
+                write_header(c, "Transfer-Encoding", "chunked").await?;
+            }
+        } else if matches!(self.method, Method::POST | Method::PUT | Method::PATCH) {
+            // Some servers require an explicit length for methods that usually carry a body
+            write_header(c, "Content-Length", "0").await?;
+        }
+        if let Some(extra_headers) = self.extra_headers.filter(|_| self.raw_headers.is_none()) {
+            for (header, value) in extra_headers.iter() {
+                write_header(c, header, value).await?;
+            }
+        }
+        if let Some(header_source) = self.header_source.filter(|_| self.raw_headers.is_none()) {
+            let mut index = 0;
+            while let Some((header, value)) = header_source.header(index) {
+                write_header(c, header, value).await?;
+                index += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the headers derived from the builder
+    async fn write_structured_headers<C: Write>(&self, c: &mut C, overridden: u16) -> Result<(), Error> {
+        if let Some(auth) = self
+            .auth
+            .as_ref()
+            .filter(|_| self.signer.is_none() && !is_overridden(overridden, "Authorization"))
+        {
+            match auth {
+                Auth::Basic { username, password } => {
+                    let mut combined: String<128> = String::new();
+                    write!(combined, "{}:{}", username, password).map_err(|_| Error::Codec)?;
+                    write_basic_auth(c, combined.as_bytes()).await?;
+                }
+                Auth::Userinfo(userinfo) => {
+                    let mut combined = [0; 128];
+                    let combined = percent::decode(userinfo.as_bytes(), &mut combined).map_err(|_| Error::Codec)?;
+                    write_basic_auth(c, combined).await?;
+                }
+            }
+        }
+        if let Some(host) = self.host {
+            match self.host_port.filter(|_| !is_overridden(overridden, "Host")) {
+                Some(port) => {
+                    let mut s: String<6> = String::new();
+                    write!(s, ":{}", port).map_err(|_| Error::Codec)?;
+                    write_str(c, "Host: ").await?;
+                    write_str(c, host).await?;
+                    write_str(c, s.as_str()).await?;
+                    write_str(c, "\r\n").await?;
+                }
+                None => write_structured_header(c, overridden, "Host", host).await?,
+            }
+        }
+        if let Some(user_agent) = self.user_agent {
+            write_structured_header(c, overridden, "User-Agent", user_agent).await?;
+        }
+        // Connections are not persistent by default in HTTP/1.0
+        let keep_alive = self
+            .keep_alive
+            .or((self.version == HttpVersion::Http10).then_some(false));
+        if let Some(keep_alive) = keep_alive {
+            let connection = if keep_alive { "keep-alive" } else { "close" };
+            write_structured_header(c, overridden, "Connection", connection).await?;
+        }
+        if let Some(content_type) = &self.content_type {
+            match self.charset.filter(|_| !is_overridden(overridden, "Content-Type")) {
+                Some(charset) => {
+                    write_str(c, "Content-Type: ").await?;
+                    write_str(c, content_type.as_str()).await?;
+                    write_str(c, "; charset=").await?;
+                    write_str(c, charset).await?;
+                    write_str(c, "\r\n").await?;
+                }
+                None => write_structured_header(c, overridden, "Content-Type", content_type.as_str()).await?,
+            }
+        }
+        if let Some(accept) = &self.accept {
+            write_structured_header(c, overridden, "Accept", accept.as_str()).await?;
+        }
+        if let Some(accept_language) = self.accept_language {
+            write_structured_header(c, overridden, "Accept-Language", accept_language).await?;
+        }
+        if let Some(if_match) = self.if_match {
+            write_structured_header(c, overridden, "If-Match", if_match).await?;
+        }
+        if let Some(date) = self.date {
+            write_structured_header(c, overridden, "Date", date).await?;
+        }
+        if let Some(referer) = self.referer {
+            write_structured_header(c, overridden, "Referer", referer).await?;
+        }
+        Ok(())
+    }
+
+    /// Find the structured headers that are overridden by an extra header, iterating the extra headers once.
+    ///
+    /// Bit `n` of the result is set if [`STRUCTURED_HEADERS`]`[n]` is present in the extra headers.
+    fn overridden_headers(&self) -> u16 {
+        let mut overridden = 0;
+        let mut mark = |header: &str| {
+            if let Some(n) = STRUCTURED_HEADERS
+                .iter()
+                .position(|name| header.eq_ignore_ascii_case(name))
+            {
+                overridden |= 1 << n;
+            }
+        };
+        if let Some(extra_headers) = self.extra_headers {
+            extra_headers.iter().for_each(|(header, _)| mark(header));
+        }
+        if let Some(header_source) = self.header_source {
+            (0..)
+                .map_while(|index| header_source.header(index))
+                .for_each(|(header, _)| mark(header));
+        }
+        overridden
+    }
+}
+
+/// The headers derived from the builder that an extra header with the same name replaces.
+const STRUCTURED_HEADERS: [&str; 10] = [
+    "Authorization",
+    "Host",
+    "User-Agent",
+    "Connection",
+    "Content-Type",
+    "Accept",
+    "Accept-Language",
+    "If-Match",
+    "Date",
+    "Referer",
+];
+
+/// Check whether the structured header `name` is set in the `overridden` bits of [`Request::overridden_headers`].
+fn is_overridden(overridden: u16, name: &str) -> bool {
+    STRUCTURED_HEADERS
+        .iter()
+        .position(|header| *header == name)
+        .is_some_and(|n| overridden & (1 << n) != 0)
+}
+
+/// Write a header derived from the builder, unless it is explicitly overridden by an extra header.
+async fn write_structured_header<C: Write>(c: &mut C, overridden: u16, key: &str, value: &str) -> Result<(), Error> {
+    if is_overridden(overridden, key) {
+        return Ok(());
+    }
+    write_header(c, key, value).await
 }
 
+#[derive(Clone)]
 pub struct DefaultRequestBuilder<'req, B>(Request<'req, B>)
 where
     B: RequestBody;
 
+impl<'req, B> DefaultRequestBuilder<'req, B>
+where
+    B: RequestBody,
+{
+    /// Use the percent-encoded userinfo of a url for basic authentication.
+    pub(crate) fn userinfo(mut self, userinfo: Option<&'req str>) -> Self {
+        if let Some(userinfo) = userinfo {
+            self.0.auth.replace(Auth::Userinfo(userinfo));
+        }
+        self
+    }
+
+    /// Append `:port` to the `Host` header, for a port that is not part of the host in the url.
+    pub(crate) fn host_port(mut self, port: Option<u16>) -> Self {
+        self.0.host_port = port;
+        self
+    }
+}
+
 impl<'req, B> RequestBuilder<'req, B> for DefaultRequestBuilder<'req, B>
 where
     B: RequestBody,
@@ -210,26 +564,61 @@ where
         self
     }
 
+    fn version(mut self, version: HttpVersion) -> Self {
+        self.0.version = version;
+        self
+    }
+
     fn body<T: RequestBody>(self, body: T) -> Self::WithBody<T> {
         DefaultRequestBuilder(Request {
             method: self.0.method,
+            version: self.0.version,
             base_path: self.0.base_path,
             path: self.0.path,
             auth: self.0.auth,
             host: self.0.host,
+            host_port: self.0.host_port,
             body: Some(body),
             content_type: self.0.content_type,
+            accept: self.0.accept,
+            accept_language: self.0.accept_language,
             extra_headers: self.0.extra_headers,
+            raw_headers: self.0.raw_headers,
+            user_agent: self.0.user_agent,
+            keep_alive: self.0.keep_alive,
+            header_source: self.0.header_source,
+            if_match: self.0.if_match,
+            date: self.0.date,
+            signer: self.0.signer,
+            charset: self.0.charset,
+            referer: self.0.referer,
         })
     }
 
     fn host(mut self, host: &'req str) -> Self {
         self.0.host.replace(host);
+        self.0.host_port = None;
+        self
+    }
+
+    fn no_host(mut self) -> Self {
+        self.0.host = None;
         self
     }
 
-    fn content_type(mut self, content_type: ContentType) -> Self {
+    fn content_type(mut self, content_type: ContentType<'req>) -> Self {
         self.0.content_type.replace(content_type);
+        self.0.charset = None;
+        self
+    }
+
+    fn accept(mut self, content_type: ContentType<'req>) -> Self {
+        self.0.accept.replace(content_type);
+        self
+    }
+
+    fn accept_language(mut self, language: &'req str) -> Self {
+        self.0.accept_language.replace(language);
         self
     }
 
@@ -238,12 +627,58 @@ where
         self
     }
 
+    fn raw_headers(mut self, headers: &'req [u8]) -> Self {
+        self.0.raw_headers.replace(headers);
+        self
+    }
+
+    fn user_agent(mut self, user_agent: &'req str) -> Self {
+        self.0.user_agent.replace(user_agent);
+        self
+    }
+
+    fn keep_alive(mut self, keep_alive: bool) -> Self {
+        self.0.keep_alive.replace(keep_alive);
+        self
+    }
+
+    fn headers_iter(mut self, headers: &'req dyn HeaderSource) -> Self {
+        self.0.header_source.replace(headers);
+        self
+    }
+
+    fn if_match(mut self, etag: &'req str) -> Self {
+        self.0.if_match.replace(etag);
+        self
+    }
+
+    fn date(mut self, date: &'req str) -> Self {
+        self.0.date.replace(date);
+        self
+    }
+
+    fn signer(mut self, signer: &'req dyn RequestSigner) -> Self {
+        self.0.signer.replace(signer);
+        self
+    }
+
+    fn content_type_with_charset(mut self, content_type: ContentType<'req>, charset: &'req str) -> Self {
+        self.0.content_type.replace(content_type);
+        self.0.charset.replace(charset);
+        self
+    }
+
+    fn referer(mut self, referer: &'req str) -> Self {
+        self.0.referer.replace(referer);
+        self
+    }
+
     fn build(self) -> Request<'req, B> {
         self.0
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// HTTP request methods
 pub enum Method {
@@ -257,6 +692,14 @@ pub enum Method {
     DELETE,
     /// HEAD
     HEAD,
+    /// OPTIONS
+    OPTIONS,
+    /// PATCH
+    PATCH,
+    /// TRACE
+    TRACE,
+    /// CONNECT, see [`Request::connect`]
+    CONNECT,
 }
 
 impl Method {
@@ -268,6 +711,57 @@ impl Method {
             Method::GET => "GET",
             Method::DELETE => "DELETE",
             Method::HEAD => "HEAD",
+            Method::OPTIONS => "OPTIONS",
+            Method::PATCH => "PATCH",
+            Method::TRACE => "TRACE",
+            Method::CONNECT => "CONNECT",
+        }
+    }
+
+    /// Parse a method token, returning `None` for unknown methods.
+    ///
+    /// The comparison is case-sensitive, so `get` is not the `GET` method.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(token: &str) -> Option<Self> {
+        match token {
+            "GET" => Some(Method::GET),
+            "PUT" => Some(Method::PUT),
+            "POST" => Some(Method::POST),
+            "DELETE" => Some(Method::DELETE),
+            "HEAD" => Some(Method::HEAD),
+            "OPTIONS" => Some(Method::OPTIONS),
+            "PATCH" => Some(Method::PATCH),
+            "TRACE" => Some(Method::TRACE),
+            "CONNECT" => Some(Method::CONNECT),
+            _ => None,
+        }
+    }
+
+    /// Whether the method is idempotent, i.e. whether sending the request multiple times has the same effect as sending it once
+    pub fn is_idempotent(&self) -> bool {
+        matches!(
+            self,
+            Method::GET | Method::PUT | Method::DELETE | Method::HEAD | Method::OPTIONS | Method::TRACE
+        )
+    }
+}
+
+/// HTTP protocol versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HttpVersion {
+    /// HTTP/1.0
+    Http10,
+    /// HTTP/1.1
+    Http11,
+}
+
+impl HttpVersion {
+    /// str representation of the version
+    pub fn as_str(&self) -> &str {
+        match self {
+            HttpVersion::Http10 => "HTTP/1.0",
+            HttpVersion::Http11 => "HTTP/1.1",
         }
     }
 }
@@ -277,6 +771,32 @@ async fn write_str<C: Write>(c: &mut C, data: &str) -> Result<(), Error> {
     Ok(())
 }
 
+/// Write the authority-form target of a CONNECT request as is.
+///
+/// An authority is not percent-encoded, as that would also encode the brackets of an IPv6 address,
+/// so an authority that contains whitespace or control characters is rejected instead.
+async fn write_authority<C: Write>(c: &mut C, authority: &str) -> Result<(), Error> {
+    if authority.is_empty() || authority.bytes().any(|b| b == b' ' || b.is_ascii_control()) {
+        return Err(Error::Codec);
+    }
+    write_str(c, authority).await
+}
+
+/// Write a path and optional query, percent-encoding the characters that are not allowed in a request target.
+async fn write_path<C: Write>(c: &mut C, path: &str) -> Result<(), Error> {
+    let bytes = path.as_bytes();
+    let mut start = 0;
+    for (index, byte) in bytes.iter().enumerate() {
+        if percent::needs_encoding(bytes, index) {
+            c.write_all(&bytes[start..index]).await.map_err(to_errorkind)?;
+            c.write_all(&percent::encode_byte(*byte)).await.map_err(to_errorkind)?;
+            start = index + 1;
+        }
+    }
+    c.write_all(&bytes[start..]).await.map_err(to_errorkind)?;
+    Ok(())
+}
+
 async fn write_header<C: Write>(c: &mut C, key: &str, value: &str) -> Result<(), Error> {
     write_str(c, key).await?;
     write_str(c, ": ").await?;
@@ -285,6 +805,20 @@ async fn write_header<C: Write>(c: &mut C, key: &str, value: &str) -> Result<(),
     Ok(())
 }
 
+/// Write the basic authorization header for the `username:password` credentials.
+async fn write_basic_auth<C: Write>(c: &mut C, credentials: &[u8]) -> Result<(), Error> {
+    use base64::engine::{general_purpose, Engine as _};
+
+    let mut authz = [0; 256];
+    let authz_len = general_purpose::STANDARD
+        .encode_slice(credentials, &mut authz)
+        .map_err(|_| Error::Codec)?;
+    write_str(c, "Authorization: Basic ").await?;
+    write_str(c, unsafe { core::str::from_utf8_unchecked(&authz[..authz_len]) }).await?;
+    write_str(c, "\r\n").await?;
+    Ok(())
+}
+
 /// The request body
 #[allow(clippy::len_without_is_empty)]
 pub trait RequestBody {
@@ -298,6 +832,13 @@ pub trait RequestBody {
 
     /// Write the body to the provided writer
     async fn write<W: Write>(&self, writer: &mut W) -> Result<(), W::Error>;
+
+    /// Take the error that ended the last [`write`](RequestBody::write) early, if any
+    ///
+    /// Bodies that read from another source report its errors here, as they cannot be returned as writer errors.
+    fn take_error(&self) -> Option<Error> {
+        None
+    }
 }
 
 impl RequestBody for () {
@@ -335,39 +876,435 @@ where
             Ok(())
         }
     }
+
+    fn take_error(&self) -> Option<Error> {
+        self.as_ref().and_then(|inner| inner.take_error())
+    }
 }
 
-pub struct FixedBodyWriter<'a, C: Write>(&'a mut C, usize);
+/// A request body that is made of multiple slices, such as a header, data and footer.
+///
+/// The slices are written in order without copying them into one buffer, and the length of the body
+/// is the total length of the slices.
+pub struct ScatterBody<'a>(pub &'a [&'a [u8]]);
 
-impl<C> ErrorType for FixedBodyWriter<'_, C>
-where
-    C: Write,
-{
-    type Error = C::Error;
+impl RequestBody for ScatterBody<'_> {
+    fn len(&self) -> Option<usize> {
+        Some(self.0.iter().map(|slice| <[u8]>::len(slice)).sum())
+    }
+
+    async fn write<W: Write>(&self, writer: &mut W) -> Result<(), W::Error> {
+        for slice in self.0 {
+            writer.write_all(slice).await?;
+        }
+        Ok(())
+    }
 }
 
-impl<C> Write for FixedBodyWriter<'_, C>
+/// A request body that is always sent using chunked encoding.
+///
+/// Each write of the inner body is sent as a separate chunk, even if the length of the inner body is known.
+/// An empty body such as `ChunkedBody(())` is sent as only the terminating chunk.
+pub struct ChunkedBody<B>(pub B);
+
+impl<B> RequestBody for ChunkedBody<B>
 where
-    C: Write,
+    B: RequestBody,
 {
-    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
-        let written = self.0.write(buf).await?;
-        self.1 += written;
-        Ok(written)
+    fn len(&self) -> Option<usize> {
+        None
     }
 
-    async fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
-        self.0.write_all(buf).await?;
-        self.1 += buf.len();
-        Ok(())
+    async fn write<W: Write>(&self, writer: &mut W) -> Result<(), W::Error> {
+        self.0.write(writer).await
     }
 
-    async fn flush(&mut self) -> Result<(), Self::Error> {
-        self.0.flush().await
+    fn take_error(&self) -> Option<Error> {
+        self.0.take_error()
     }
 }
 
-pub struct ChunkedBodyWriter<'a, C: Write>(&'a mut C, usize);
+/// A request body that is generated on demand by a closure.
+///
+/// The closure is called repeatedly and each returned chunk is written to the connection,
+/// until the closure returns `None`. Empty chunks are skipped.
+pub struct FnBody<F> {
+    generator: RefCell<F>,
+    len: Option<usize>,
+}
+
+impl<F, Fut, T> FnBody<F>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Option<T>>,
+    T: AsRef<[u8]>,
+{
+    /// Create a body of unknown length, which is sent using chunked encoding.
+    pub fn new(generator: F) -> Self {
+        Self {
+            generator: RefCell::new(generator),
+            len: None,
+        }
+    }
+
+    /// Create a body of a known length, which is sent with a `Content-Length` header.
+    ///
+    /// The request fails with [`Error::IncorrectBodyWritten`] if the closure does not generate exactly `len` bytes.
+    pub fn with_len(len: usize, generator: F) -> Self {
+        Self {
+            generator: RefCell::new(generator),
+            len: Some(len),
+        }
+    }
+}
+
+impl<F, Fut, T> RequestBody for FnBody<F>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Option<T>>,
+    T: AsRef<[u8]>,
+{
+    fn len(&self) -> Option<usize> {
+        self.len
+    }
+
+    async fn write<W: Write>(&self, writer: &mut W) -> Result<(), W::Error> {
+        loop {
+            // The generated future does not borrow the generator, so it is released before awaiting
+            let next = (self.generator.borrow_mut())();
+            let Some(chunk) = next.await else {
+                break;
+            };
+            let chunk = chunk.as_ref();
+            if !chunk.is_empty() {
+                writer.write_all(chunk).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A request body of a known length that is streamed from a reader.
+///
+/// The body is sent with a `Content-Length` header, for servers that do not support chunked request bodies.
+/// The request fails with [`Error::BodyLengthMismatch`] if the reader ends before `len` bytes are read,
+/// or if it has more data after them, and with the error of the reader if it fails.
+/// No more than `len` bytes are ever written, but the connection cannot be reused after such a failure.
+pub struct SizedStreamBody<R> {
+    reader: RefCell<R>,
+    len: usize,
+    error: Cell<Option<Error>>,
+}
+
+impl<R> SizedStreamBody<R>
+where
+    R: Read,
+{
+    /// Create a body that streams exactly `len` bytes from `reader`.
+    pub fn new(reader: R, len: usize) -> Self {
+        Self {
+            reader: RefCell::new(reader),
+            len,
+            error: Cell::new(None),
+        }
+    }
+}
+
+impl<R> RequestBody for SizedStreamBody<R>
+where
+    R: Read,
+{
+    fn len(&self) -> Option<usize> {
+        Some(self.len)
+    }
+
+    #[allow(clippy::await_holding_refcell_ref)]
+    async fn write<W: Write>(&self, writer: &mut W) -> Result<(), W::Error> {
+        // The body is written once per request, so the reader is never borrowed concurrently
+        let mut reader = self.reader.borrow_mut();
+        let mut buf = [0; 128];
+        let mut remaining = self.len;
+        while remaining > 0 {
+            let len = buf.len().min(remaining);
+            match reader.read(&mut buf[..len]).await {
+                Ok(0) => {
+                    warn!("Stream ended with {} body bytes remaining", remaining);
+                    self.error.set(Some(Error::BodyLengthMismatch));
+                    return Ok(());
+                }
+                Ok(n) => {
+                    writer.write_all(&buf[..n]).await?;
+                    remaining -= n;
+                }
+                Err(e) => {
+                    self.error.set(Some(e.kind().into()));
+                    return Ok(());
+                }
+            }
+        }
+
+        // Extra data is not written, as it would be read as the start of the next response by the server
+        match reader.read(&mut buf[..1]).await {
+            Ok(0) => {}
+            Ok(_) => {
+                warn!("Stream has more data than the body length");
+                self.error.set(Some(Error::BodyLengthMismatch));
+            }
+            Err(e) => self.error.set(Some(e.kind().into())),
+        }
+        Ok(())
+    }
+
+    fn take_error(&self) -> Option<Error> {
+        self.error.take()
+    }
+}
+
+/// A request body that reports the upload progress to a callback.
+///
+/// The callback is called with the number of bytes written so far and the total length of the body, if known,
+/// after each write of the inner body.
+pub struct ProgressBody<B, F> {
+    body: B,
+    callback: RefCell<F>,
+}
+
+impl<B, F> ProgressBody<B, F>
+where
+    B: RequestBody,
+    F: FnMut(usize, Option<usize>),
+{
+    /// Wrap a body to report its upload progress to `callback`.
+    pub fn new(body: B, callback: F) -> Self {
+        Self {
+            body,
+            callback: RefCell::new(callback),
+        }
+    }
+}
+
+impl<B, F> RequestBody for ProgressBody<B, F>
+where
+    B: RequestBody,
+    F: FnMut(usize, Option<usize>),
+{
+    fn len(&self) -> Option<usize> {
+        self.body.len()
+    }
+
+    async fn write<W: Write>(&self, writer: &mut W) -> Result<(), W::Error> {
+        let mut writer = ProgressWriter {
+            writer,
+            written: 0,
+            total: self.body.len(),
+            callback: &self.callback,
+        };
+        self.body.write(&mut writer).await
+    }
+
+    fn take_error(&self) -> Option<Error> {
+        self.body.take_error()
+    }
+}
+
+struct ProgressWriter<'a, W, F> {
+    writer: &'a mut W,
+    written: usize,
+    total: Option<usize>,
+    callback: &'a RefCell<F>,
+}
+
+impl<W, F> ErrorType for ProgressWriter<'_, W, F>
+where
+    W: Write,
+{
+    type Error = W::Error;
+}
+
+impl<W, F> Write for ProgressWriter<'_, W, F>
+where
+    W: Write,
+    F: FnMut(usize, Option<usize>),
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let written = self.writer.write(buf).await?;
+        if written > 0 {
+            self.written += written;
+            (self.callback.borrow_mut())(self.written, self.total);
+        }
+        Ok(written)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.writer.flush().await
+    }
+}
+
+/// A writer for the body of a request that is streamed without building a [`Request`].
+///
+/// The request line and headers are written when the writer is created, see
+/// [`HttpConnection::write_request_head`](crate::client::HttpConnection::write_request_head).
+/// Call [`BodyWriter::with_length`] before writing to send a body of known length,
+/// otherwise the body is sent using chunked encoding.
+/// The request is complete when [`BodyWriter::finish`] returns, after which the response can be read.
+pub struct BodyWriter<'a, C: Write> {
+    conn: &'a mut C,
+    method: Method,
+    state: BodyWriterState,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BodyWriterState {
+    /// The header section is not terminated yet
+    Head,
+    FixedLength {
+        len: usize,
+        written: usize,
+    },
+    Chunked,
+}
+
+impl<'a, C> BodyWriter<'a, C>
+where
+    C: Write,
+{
+    /// Write the request line and the headers, leaving the header section open.
+    pub(crate) async fn new(
+        conn: &'a mut C,
+        method: Method,
+        path: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<Self, Error> {
+        write_str(conn, method.as_str()).await?;
+        write_str(conn, " ").await?;
+        write_path(conn, path).await?;
+        write_str(conn, " ").await?;
+        write_str(conn, HttpVersion::Http11.as_str()).await?;
+        write_str(conn, "\r\n").await?;
+        for (header, value) in headers {
+            write_header(conn, header, value).await?;
+        }
+
+        Ok(Self {
+            conn,
+            method,
+            state: BodyWriterState::Head,
+        })
+    }
+
+    /// Send the body with a `Content-Length` header instead of using chunked encoding.
+    ///
+    /// This must be called before anything is written. Exactly `len` bytes must then be written
+    /// before calling [`BodyWriter::finish`].
+    pub async fn with_length(mut self, len: usize) -> Result<Self, Error> {
+        if self.state != BodyWriterState::Head {
+            return Err(Error::AlreadySent);
+        }
+
+        let mut s: String<32> = String::new();
+        write!(s, "{}", len).map_err(|_| Error::Codec)?;
+        write_header(self.conn, "Content-Length", s.as_str()).await?;
+        write_str(self.conn, "\r\n").await?;
+        self.state = BodyWriterState::FixedLength { len, written: 0 };
+        Ok(self)
+    }
+
+    /// Complete the request and flush the connection.
+    ///
+    /// Returns [`Error::IncorrectBodyWritten`] if a body of known length was not written completely.
+    pub async fn finish(self) -> Result<(), Error> {
+        match self.state {
+            BodyWriterState::Head => {
+                if matches!(self.method, Method::POST | Method::PUT | Method::PATCH) {
+                    write_header(self.conn, "Content-Length", "0").await?;
+                }
+                write_str(self.conn, "\r\n").await?;
+            }
+            BodyWriterState::FixedLength { len, written } if len != written => {
+                return Err(Error::IncorrectBodyWritten);
+            }
+            BodyWriterState::FixedLength { .. } => {}
+            BodyWriterState::Chunked => write_str(self.conn, "0\r\n\r\n").await?,
+        }
+
+        self.conn.flush().await.map_err(|e| e.kind())?;
+        Ok(())
+    }
+}
+
+impl<C> ErrorType for BodyWriter<'_, C>
+where
+    C: Write,
+{
+    type Error = Error;
+}
+
+impl<C> Write for BodyWriter<'_, C>
+where
+    C: Write,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.write_all(buf).await?;
+        Ok(buf.len())
+    }
+
+    async fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        if self.state == BodyWriterState::Head {
+            write_header(self.conn, "Transfer-Encoding", "chunked").await?;
+            write_str(self.conn, "\r\n").await?;
+            self.state = BodyWriterState::Chunked;
+        }
+
+        match &mut self.state {
+            BodyWriterState::FixedLength { len, written } => {
+                if *written + buf.len() > *len {
+                    return Err(Error::IncorrectBodyWritten);
+                }
+                self.conn.write_all(buf).await.map_err(to_errorkind)?;
+                *written += buf.len();
+            }
+            _ => ChunkedBodyWriter(self.conn, 0).write_all(buf).await?,
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.conn.flush().await.map_err(|e| e.kind())?;
+        Ok(())
+    }
+}
+
+pub struct FixedBodyWriter<'a, C: Write>(&'a mut C, usize);
+
+impl<C> ErrorType for FixedBodyWriter<'_, C>
+where
+    C: Write,
+{
+    type Error = C::Error;
+}
+
+impl<C> Write for FixedBodyWriter<'_, C>
+where
+    C: Write,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let written = self.0.write(buf).await?;
+        self.1 += written;
+        Ok(written)
+    }
+
+    async fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.0.write_all(buf).await?;
+        self.1 += buf.len();
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.0.flush().await
+    }
+}
+
+pub struct ChunkedBodyWriter<'a, C: Write>(&'a mut C, usize);
 
 impl<C> ErrorType for ChunkedBodyWriter<'_, C>
 where
@@ -390,6 +1327,11 @@ where
     }
 
     async fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        // An empty chunk terminates the body, so empty writes are skipped
+        if buf.is_empty() {
+            return Ok(());
+        }
+
         // Write chunk header
         let len = buf.len();
         let mut hex = [0; 2 * size_of::<usize>()];
@@ -416,6 +1358,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::headers::HttpDate;
 
     #[tokio::test]
     async fn basic_auth() {
@@ -434,57 +1377,905 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn with_empty_body() {
+    async fn with_accept_headers() {
         let mut buffer = Vec::new();
-        Request::new(Method::POST, "/")
-            .body([].as_slice())
+        Request::new(Method::GET, "/")
+            .accept(ContentType::ApplicationJson)
+            .accept_language("en-US")
             .build()
             .write(&mut buffer)
             .await
             .unwrap();
 
-        assert_eq!(b"POST / HTTP/1.1\r\nContent-Length: 0\r\n\r\n", buffer.as_slice());
+        assert_eq!(
+            b"GET / HTTP/1.1\r\nAccept: application/json\r\nAccept-Language: en-US\r\n\r\n",
+            buffer.as_slice()
+        );
     }
 
     #[tokio::test]
-    async fn with_known_body() {
+    async fn extra_headers_take_precedence() {
         let mut buffer = Vec::new();
-        Request::new(Method::POST, "/")
-            .body(b"BODY".as_slice())
+        Request::new(Method::GET, "/")
+            .accept(ContentType::ApplicationJson)
+            .headers(&[("accept", "text/plain")])
             .build()
             .write(&mut buffer)
             .await
             .unwrap();
 
-        assert_eq!(b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nBODY", buffer.as_slice());
+        assert_eq!(b"GET / HTTP/1.1\r\naccept: text/plain\r\n\r\n", buffer.as_slice());
     }
 
-    struct ChunkedBody<'a>(&'a [u8]);
+    #[tokio::test]
+    async fn with_referer() {
+        let mut buffer = Vec::new();
+        Request::new(Method::GET, "/")
+            .referer("https://example.com/page")
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
 
-    impl RequestBody for ChunkedBody<'_> {
-        fn len(&self) -> Option<usize> {
-            None // Unknown length: triggers chunked body
-        }
+        assert_eq!(
+            b"GET / HTTP/1.1\r\nReferer: https://example.com/page\r\n\r\n",
+            buffer.as_slice()
+        );
 
-        async fn write<W: Write>(&self, writer: &mut W) -> Result<(), W::Error> {
-            writer.write_all(self.0).await
-        }
+        let mut buffer = Vec::new();
+        Request::new(Method::GET, "/")
+            .referer("https://example.com/page")
+            .headers(&[("referer", "https://example.com/other")])
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"GET / HTTP/1.1\r\nreferer: https://example.com/other\r\n\r\n",
+            buffer.as_slice()
+        );
     }
 
     #[tokio::test]
-    async fn with_unknown_body() {
+    async fn headers_from_iterator() {
+        let names = ["x-first", "x-second", "accept"];
+        let headers = HeaderIter::new(names.iter().map(|name| (*name, "1")));
         let mut buffer = Vec::new();
+        Request::new(Method::GET, "/")
+            .accept(ContentType::ApplicationJson)
+            .headers(&[("x-slice", "0")])
+            .headers_iter(&headers)
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
 
-        Request::new(Method::POST, "/")
-            .body(ChunkedBody(b"BODY".as_slice()))
+        assert_eq!(
+            b"GET / HTTP/1.1\r\nx-slice: 0\r\nx-first: 1\r\nx-second: 1\r\naccept: 1\r\n\r\n",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn headers_from_iterator_are_iterated_once_per_pass() {
+        let names = ["x-first", "x-second", "user-agent"];
+        let yielded = Cell::new(0);
+        let headers = HeaderIter::new(names.iter().map(|name| {
+            yielded.set(yielded.get() + 1);
+            (*name, "1")
+        }));
+        let mut buffer = Vec::new();
+        Request::new(Method::GET, "/")
+            .host("example.com")
+            .user_agent("reqwless")
+            .accept(ContentType::ApplicationJson)
+            .headers_iter(&headers)
             .build()
             .write(&mut buffer)
             .await
             .unwrap();
 
         assert_eq!(
-            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nBODY\r\n0\r\n\r\n",
+            b"GET / HTTP/1.1\r\nHost: example.com\r\nAccept: application/json\r\nx-first: 1\r\nx-second: 1\r\nuser-agent: 1\r\n\r\n",
+            buffer.as_slice()
+        );
+        // One pass to find the overridden headers and one to write them
+        assert_eq!(2 * names.len(), yielded.get());
+    }
+
+    #[tokio::test]
+    async fn headers_from_heapless_vec() {
+        let mut headers: heapless::Vec<(&str, &str), 2> = heapless::Vec::new();
+        headers.push(("x-first", "1")).unwrap();
+        headers.push(("x-second", "2")).unwrap();
+        let headers = HeaderIter::new(&headers);
+        let mut buffer = Vec::new();
+        Request::new(Method::GET, "/")
+            .headers_iter(&headers)
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"GET / HTTP/1.1\r\nx-first: 1\r\nx-second: 2\r\n\r\n",
             buffer.as_slice()
         );
     }
+
+    #[tokio::test]
+    async fn put_with_if_match() {
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::put("/config")
+            .if_match("\"v1\"")
+            .body(b"{}".as_slice())
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"PUT /config HTTP/1.1\r\nIf-Match: \"v1\"\r\nContent-Length: 2\r\n\r\n{}",
+            buffer.as_slice()
+        );
+    }
+
+    #[derive(Default)]
+    struct RecordingSigner(core::cell::RefCell<Vec<u8>>);
+
+    impl RequestSigner for RecordingSigner {
+        fn begin(&self) {
+            self.0.borrow_mut().clear();
+        }
+
+        fn update(&self, bytes: &[u8]) {
+            self.0.borrow_mut().extend_from_slice(bytes);
+        }
+
+        fn finish<'b>(&self, buf: &'b mut [u8]) -> Result<&'b str, Error> {
+            buf[..9].copy_from_slice(b"Signed 42");
+            Ok(core::str::from_utf8(&buf[..9])?)
+        }
+    }
+
+    #[tokio::test]
+    async fn signer_signs_head_and_sets_authorization() {
+        let signer = RecordingSigner::default();
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::post("/")
+            .host("example.com")
+            .basic_auth("username", "password")
+            .signer(&signer)
+            .body(b"BODY".as_slice())
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 4\r\n",
+            signer.0.borrow().as_slice()
+        );
+        assert_eq!(
+            b"POST / HTTP/1.1\r\nHost: example.com\r\nContent-Length: 4\r\nAuthorization: Signed 42\r\n\r\nBODY",
+            buffer.as_slice()
+        );
+    }
+
+    #[test]
+    fn method_from_str() {
+        for method in [
+            Method::GET,
+            Method::PUT,
+            Method::POST,
+            Method::DELETE,
+            Method::HEAD,
+            Method::OPTIONS,
+            Method::PATCH,
+            Method::TRACE,
+            Method::CONNECT,
+        ] {
+            assert_eq!(Some(method), Method::from_str(method.as_str()));
+        }
+        assert_eq!(None, Method::from_str("get"));
+        assert_eq!(None, Method::from_str("PROPFIND"));
+    }
+
+    #[tokio::test]
+    async fn connect_uses_authority_form() {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut request = Request::connect("example.com:443").host("example.com:443").build();
+        request.base_path = Some("/api");
+        request.write(&mut buffer).await.unwrap();
+
+        assert_eq!(
+            b"CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\n",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn connect_writes_ipv6_authority_as_is() {
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::connect("[::1]:443").build().write(&mut buffer).await.unwrap();
+
+        assert_eq!(b"CONNECT [::1]:443 HTTP/1.1\r\n\r\n", buffer.as_slice());
+    }
+
+    #[tokio::test]
+    async fn connect_with_invalid_authority_fails() {
+        for authority in ["", "example.com:443 HTTP/1.0", "example.com:443\r\nX-Injected: yes"] {
+            let mut buffer: Vec<u8> = Vec::new();
+            let result = Request::connect(authority).build().write(&mut buffer).await;
+
+            assert!(matches!(result, Err(Error::Codec)));
+        }
+    }
+
+    #[tokio::test]
+    async fn with_content_type_with_charset() {
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::post("/")
+            .content_type_with_charset(ContentType::TextPlain, "utf-8")
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"POST / HTTP/1.1\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: 0\r\n\r\n",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn with_date() {
+        let date = HttpDate::from_unix(784111777);
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::get("/")
+            .date(date.as_str())
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"GET / HTTP/1.1\r\nDate: Sun, 06 Nov 1994 08:49:37 GMT\r\n\r\n",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn patch_with_body() {
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::patch("/")
+            .host("localhost")
+            .body(b"{}".as_slice())
+            .content_type(ContentType::ApplicationJson)
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"PATCH / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn progress_body_reports_written_bytes() {
+        let mut progress = Vec::new();
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::new(Method::POST, "/")
+            .body(ProgressBody::new(b"BODY".as_slice(), |written, total| {
+                progress.push((written, total))
+            }))
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nBODY", buffer.as_slice());
+        assert_eq!(vec![(4, Some(4))], progress);
+    }
+
+    #[tokio::test]
+    async fn progress_body_reports_chunked_bytes() {
+        let mut progress = Vec::new();
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::new(Method::POST, "/")
+            .body(ProgressBody::new(ChunkedBody(MultiWriteBody), |written, total| {
+                progress.push((written, total))
+            }))
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(vec![(2, None), (4, None)], progress);
+    }
+
+    #[tokio::test]
+    async fn with_keep_alive() {
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::new(Method::GET, "/")
+            .host("localhost")
+            .keep_alive(false)
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn extra_connection_header_takes_precedence() {
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::new(Method::GET, "/")
+            .keep_alive(true)
+            .headers(&[("connection", "Upgrade")])
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(b"GET / HTTP/1.1\r\nconnection: Upgrade\r\n\r\n", buffer.as_slice());
+    }
+
+    #[tokio::test]
+    async fn bodyless_post_has_zero_content_length() {
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::post("/").build().write(&mut buffer).await.unwrap();
+
+        assert_eq!(b"POST / HTTP/1.1\r\nContent-Length: 0\r\n\r\n", buffer.as_slice());
+    }
+
+    #[tokio::test]
+    async fn bodyless_get_has_no_content_length() {
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::get("/").build().write(&mut buffer).await.unwrap();
+
+        assert_eq!(b"GET / HTTP/1.1\r\n\r\n", buffer.as_slice());
+    }
+
+    async fn write_joined_path(base_path: &str, path: &str) -> String<64> {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut request = Request::get(path).build();
+        request.base_path = Some(base_path);
+        request.write(&mut buffer).await.unwrap();
+
+        let request_line = core::str::from_utf8(&buffer).unwrap().split("\r\n").next().unwrap();
+        request_line.try_into().unwrap()
+    }
+
+    #[tokio::test]
+    async fn base_path_and_path_are_joined_with_one_slash() {
+        assert_eq!("GET /api/v1 HTTP/1.1", write_joined_path("/api/", "/v1").await);
+        assert_eq!("GET /api/v1 HTTP/1.1", write_joined_path("/api", "v1").await);
+        assert_eq!("GET /api/v1 HTTP/1.1", write_joined_path("/api", "/v1").await);
+        assert_eq!("GET /api/v1 HTTP/1.1", write_joined_path("/api//", "//v1").await);
+        assert_eq!("GET /api/ HTTP/1.1", write_joined_path("/api", "/").await);
+    }
+
+    #[tokio::test]
+    async fn empty_base_path_or_path_is_joined() {
+        assert_eq!("GET /v1 HTTP/1.1", write_joined_path("", "v1").await);
+        assert_eq!("GET /v1 HTTP/1.1", write_joined_path("/", "/v1").await);
+        assert_eq!("GET /api HTTP/1.1", write_joined_path("/api", "").await);
+        assert_eq!("GET / HTTP/1.1", write_joined_path("", "").await);
+    }
+
+    #[tokio::test]
+    async fn body_writer_with_length() {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut writer = BodyWriter::new(&mut buffer, Method::PUT, "/upload", &[("Host", "localhost")])
+            .await
+            .unwrap()
+            .with_length(4)
+            .await
+            .unwrap();
+        writer.write_all(b"BO").await.unwrap();
+        writer.write_all(b"DY").await.unwrap();
+        writer.finish().await.unwrap();
+
+        assert_eq!(
+            b"PUT /upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 4\r\n\r\nBODY",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn body_writer_chunked() {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut writer = BodyWriter::new(&mut buffer, Method::POST, "/", &[]).await.unwrap();
+        writer.write_all(b"BO").await.unwrap();
+        writer.write_all(b"DY").await.unwrap();
+        writer.finish().await.unwrap();
+
+        assert_eq!(
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nBO\r\n2\r\nDY\r\n0\r\n\r\n",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn body_writer_with_incorrect_length() {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut writer = BodyWriter::new(&mut buffer, Method::POST, "/", &[])
+            .await
+            .unwrap()
+            .with_length(4)
+            .await
+            .unwrap();
+        writer.write_all(b"BO").await.unwrap();
+
+        assert!(matches!(
+            writer.write_all(b"DYX").await,
+            Err(Error::IncorrectBodyWritten)
+        ));
+        assert!(matches!(writer.finish().await, Err(Error::IncorrectBodyWritten)));
+    }
+
+    #[tokio::test]
+    async fn with_user_agent() {
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::new(Method::GET, "/")
+            .host("localhost")
+            .user_agent("reqwless/test")
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"GET / HTTP/1.1\r\nHost: localhost\r\nUser-Agent: reqwless/test\r\n\r\n",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn extra_user_agent_header_takes_precedence() {
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::new(Method::GET, "/")
+            .user_agent("reqwless/test")
+            .headers(&[("user-agent", "custom")])
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(b"GET / HTTP/1.1\r\nuser-agent: custom\r\n\r\n", buffer.as_slice());
+    }
+
+    #[tokio::test]
+    async fn with_raw_headers() {
+        let mut buffer = Vec::new();
+        Request::new(Method::POST, "/")
+            .host("example.com")
+            .headers(&[("X-Ignored", "true")])
+            .raw_headers(b"x-custom: a\r\nHOST: example.com\r\n")
+            .body(b"BODY".as_slice())
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"POST / HTTP/1.1\r\nx-custom: a\r\nHOST: example.com\r\nContent-Length: 4\r\n\r\nBODY",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn no_host() {
+        let mut buffer: Vec<u8> = Vec::new();
+        Request::new(Method::GET, "/")
+            .host("example.com")
+            .no_host()
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(b"GET / HTTP/1.1\r\n\r\n", buffer.as_slice());
+    }
+
+    #[tokio::test]
+    async fn http10_closes_connection_by_default() {
+        let mut buffer = Vec::new();
+        Request::new(Method::GET, "/")
+            .host("example.com")
+            .version(HttpVersion::Http10)
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"GET / HTTP/1.0\r\nHost: example.com\r\nConnection: close\r\n\r\n",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn http10_can_omit_host_and_keep_connection_alive() {
+        let mut buffer = Vec::new();
+        Request::new(Method::GET, "/")
+            .host("example.com")
+            .no_host()
+            .keep_alive(true)
+            .version(HttpVersion::Http10)
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(b"GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n", buffer.as_slice());
+    }
+
+    #[tokio::test]
+    async fn http10_rejects_chunked_body() {
+        let mut buffer = Vec::new();
+        let result = Request::new(Method::POST, "/")
+            .version(HttpVersion::Http10)
+            .body(ChunkedBody(b"BODY".as_slice()))
+            .build()
+            .write(&mut buffer)
+            .await;
+
+        assert!(matches!(result, Err(Error::Codec)));
+        assert!(buffer.is_empty());
+
+        let mut buffer = Vec::new();
+        Request::new(Method::POST, "/")
+            .version(HttpVersion::Http10)
+            .body(b"BODY".as_slice())
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert!(buffer.ends_with(b"Content-Length: 4\r\n\r\nBODY"));
+    }
+
+    #[tokio::test]
+    async fn path_is_percent_encoded() {
+        let mut buffer = Vec::new();
+        Request::new(Method::GET, "/files/my file.txt")
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(b"GET /files/my%20file.txt HTTP/1.1\r\n\r\n", buffer.as_slice());
+    }
+
+    #[tokio::test]
+    async fn path_is_not_double_encoded() {
+        let mut buffer = Vec::new();
+        Request::new(Method::GET, "/files/my%20file ä.txt?q=a b&x=%zz")
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"GET /files/my%20file%20%C3%A4.txt?q=a%20b&x=%25zz HTTP/1.1\r\n\r\n",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn with_empty_body() {
+        let mut buffer = Vec::new();
+        Request::new(Method::POST, "/")
+            .body([].as_slice())
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(b"POST / HTTP/1.1\r\nContent-Length: 0\r\n\r\n", buffer.as_slice());
+    }
+
+    #[tokio::test]
+    async fn with_known_body() {
+        let mut buffer = Vec::new();
+        Request::new(Method::POST, "/")
+            .body(b"BODY".as_slice())
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nBODY", buffer.as_slice());
+    }
+
+    #[tokio::test]
+    async fn with_scattered_body() {
+        let mut buffer = Vec::new();
+        Request::new(Method::POST, "/")
+            .body(ScatterBody(&[b"HEAD", b"", b"DATA", b"FOOT"]))
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"POST / HTTP/1.1\r\nContent-Length: 12\r\n\r\nHEADDATAFOOT",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn with_unknown_body() {
+        let mut buffer = Vec::new();
+
+        Request::new(Method::POST, "/")
+            .body(ChunkedBody(b"BODY".as_slice()))
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nBODY\r\n0\r\n\r\n",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn with_empty_chunked_body() {
+        let mut buffer = Vec::new();
+
+        Request::new(Method::POST, "/")
+            .body(ChunkedBody(()))
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n",
+            buffer.as_slice()
+        );
+    }
+
+    /// A writer that records how many bytes were written when it was last flushed
+    #[derive(Default)]
+    struct FlushTrackingWriter {
+        written: Vec<u8>,
+        flushed: Option<usize>,
+    }
+
+    impl embedded_io::ErrorType for FlushTrackingWriter {
+        type Error = embedded_io::ErrorKind;
+    }
+
+    impl Write for FlushTrackingWriter {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            self.flushed = Some(self.written.len());
+            Ok(())
+        }
+    }
+
+    /// A writer that accepts a number of bytes and then fails like a connection reset by the server
+    struct ResetWriter(usize);
+
+    impl embedded_io::ErrorType for ResetWriter {
+        type Error = embedded_io::ErrorKind;
+    }
+
+    impl Write for ResetWriter {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            if self.0 == 0 {
+                return Err(embedded_io::ErrorKind::ConnectionReset);
+            }
+            let len = buf.len().min(self.0);
+            self.0 -= len;
+            Ok(len)
+        }
+    }
+
+    #[tokio::test]
+    async fn connection_reset_while_writing_is_reported() {
+        let body = b"BODY".repeat(10);
+        // The connection is reset while writing the head or the body
+        for accepted in [0, 40] {
+            let result = Request::post("/")
+                .body(body.as_slice())
+                .build()
+                .write(&mut ResetWriter(accepted))
+                .await;
+            assert!(matches!(result, Err(Error::ConnectionReset)));
+
+            let result = Request::post("/")
+                .body(ChunkedBody(body.as_slice()))
+                .build()
+                .write(&mut ResetWriter(accepted))
+                .await;
+            assert!(matches!(result, Err(Error::ConnectionReset)));
+        }
+    }
+
+    #[tokio::test]
+    async fn write_ends_with_flush() {
+        let mut writer = FlushTrackingWriter::default();
+        Request::get("/").build().write(&mut writer).await.unwrap();
+        assert_eq!(Some(writer.written.len()), writer.flushed);
+
+        let mut writer = FlushTrackingWriter::default();
+        Request::post("/")
+            .body(b"BODY".as_slice())
+            .build()
+            .write(&mut writer)
+            .await
+            .unwrap();
+        assert!(writer.written.ends_with(b"\r\n\r\nBODY"));
+        assert_eq!(Some(writer.written.len()), writer.flushed);
+
+        let mut writer = FlushTrackingWriter::default();
+        Request::post("/")
+            .body(ChunkedBody(MultiWriteBody))
+            .build()
+            .write(&mut writer)
+            .await
+            .unwrap();
+        assert!(writer.written.ends_with(b"0\r\n\r\n"));
+        assert_eq!(Some(writer.written.len()), writer.flushed);
+    }
+
+    struct MultiWriteBody;
+
+    impl RequestBody for MultiWriteBody {
+        fn len(&self) -> Option<usize> {
+            Some(4)
+        }
+
+        async fn write<W: Write>(&self, writer: &mut W) -> Result<(), W::Error> {
+            writer.write_all(b"BO").await?;
+            writer.write_all(b"").await?;
+            writer.write_all(b"DY").await?;
+            writer.flush().await
+        }
+    }
+
+    #[tokio::test]
+    async fn chunked_body_sends_each_write_as_chunk() {
+        let mut buffer = Vec::new();
+
+        Request::new(Method::POST, "/")
+            .body(ChunkedBody(MultiWriteBody))
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nBO\r\n2\r\nDY\r\n0\r\n\r\n",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn with_generated_body() {
+        let mut buffer = Vec::new();
+        let mut chunks = [b"BO".as_slice(), b"".as_slice(), b"DY".as_slice()].into_iter();
+
+        Request::new(Method::POST, "/")
+            .body(FnBody::new(|| core::future::ready(chunks.next())))
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nBO\r\n2\r\nDY\r\n0\r\n\r\n",
+            buffer.as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn with_generated_body_of_known_length() {
+        let mut buffer = Vec::new();
+        let mut chunks = [b"BO".as_slice(), b"DY".as_slice()].into_iter();
+
+        Request::new(Method::POST, "/")
+            .body(FnBody::with_len(4, || core::future::ready(chunks.next())))
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nBODY", buffer.as_slice());
+    }
+
+    #[tokio::test]
+    async fn generated_body_of_incorrect_length_fails() {
+        let mut buffer = Vec::new();
+        let mut chunks = [b"BODY".as_slice()].into_iter();
+
+        let result = Request::new(Method::POST, "/")
+            .body(FnBody::with_len(5, || core::future::ready(chunks.next())))
+            .build()
+            .write(&mut buffer)
+            .await;
+
+        assert!(matches!(result, Err(Error::IncorrectBodyWritten)));
+    }
+
+    #[tokio::test]
+    async fn with_sized_stream_body() {
+        let mut buffer = Vec::new();
+
+        Request::new(Method::PUT, "/")
+            .body(SizedStreamBody::new(b"BODY".as_slice(), 4))
+            .build()
+            .write(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(b"PUT / HTTP/1.1\r\nContent-Length: 4\r\n\r\nBODY", buffer.as_slice());
+    }
+
+    #[tokio::test]
+    async fn short_sized_stream_body_fails() {
+        let mut buffer = Vec::new();
+        let result = Request::new(Method::PUT, "/")
+            .body(SizedStreamBody::new(b"BODY".as_slice(), 5))
+            .build()
+            .write(&mut buffer)
+            .await;
+
+        assert!(matches!(result, Err(Error::BodyLengthMismatch)));
+    }
+
+    #[tokio::test]
+    async fn long_sized_stream_body_fails_without_writing_extra_data() {
+        let mut buffer = Vec::new();
+        let result = Request::new(Method::PUT, "/")
+            .body(SizedStreamBody::new(b"BODY".as_slice(), 3))
+            .build()
+            .write(&mut buffer)
+            .await;
+
+        assert!(matches!(result, Err(Error::BodyLengthMismatch)));
+        assert_eq!(b"PUT / HTTP/1.1\r\nContent-Length: 3\r\n\r\nBOD", buffer.as_slice());
+    }
+
+    struct FailingReader;
+
+    impl ErrorType for FailingReader {
+        type Error = embedded_io::ErrorKind;
+    }
+
+    impl Read for FailingReader {
+        async fn read(&mut self, _buf: &mut [u8]) -> Result<usize, Self::Error> {
+            Err(embedded_io::ErrorKind::TimedOut)
+        }
+    }
+
+    #[tokio::test]
+    async fn sized_stream_body_passes_reader_error_through() {
+        let mut buffer = Vec::new();
+        let result = Request::new(Method::PUT, "/")
+            .body(SizedStreamBody::new(FailingReader, 4))
+            .build()
+            .write(&mut buffer)
+            .await;
+
+        assert!(matches!(result, Err(Error::Network(embedded_io::ErrorKind::TimedOut))));
+        assert_eq!(b"PUT / HTTP/1.1\r\nContent-Length: 4\r\n\r\n", buffer.as_slice());
+    }
 }
@@ -5,6 +5,11 @@ use crate::{
     Error, TryBufRead,
 };
 
+use super::{HeaderIterator, PartialBody, MAX_HEADERS};
+
+/// The maximum size of the trailer section following the last chunk that is kept
+const MAX_TRAILERS_LEN: usize = 128;
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum ChunkState {
     NoChunk,
@@ -36,16 +41,21 @@ impl ChunkState {
 pub struct ChunkedBodyReader<B> {
     pub raw_body: B,
     chunk_remaining: ChunkState,
+    collect_trailers: bool,
+    trailers: heapless::Vec<u8, MAX_TRAILERS_LEN>,
 }
 
 impl<C> ChunkedBodyReader<C>
 where
     C: Read,
 {
-    pub fn new(raw_body: C) -> Self {
+    /// Create a reader for a chunked body, which keeps the trailers sent after the last chunk if `collect_trailers` is set.
+    pub fn new(raw_body: C, collect_trailers: bool) -> Self {
         Self {
             raw_body,
             chunk_remaining: ChunkState::NoChunk,
+            collect_trailers,
+            trailers: heapless::Vec::new(),
         }
     }
 
@@ -53,6 +63,20 @@ where
         self.chunk_remaining == ChunkState::Empty
     }
 
+    /// Get the trailer headers sent after the last chunk.
+    ///
+    /// Returns `None` until the body is completely read, or if the server did not send any trailers.
+    /// Trailers are only kept if they were advertised, and a trailer is skipped if it does not fit.
+    pub fn trailers(&self) -> Option<HeaderIterator<'_>> {
+        if !self.is_done() || self.trailers.is_empty() {
+            return None;
+        }
+
+        let mut iterator = HeaderIterator(0, [httparse::EMPTY_HEADER; MAX_HEADERS]);
+        httparse::parse_headers(&self.trailers, &mut iterator.1).ok()?;
+        Some(iterator)
+    }
+
     async fn read_next_chunk_length(&mut self) -> Result<(), Error> {
         let mut header_buf = [0; 8 + 2]; // 32 bit hex + \r + \n
         let mut total_read = 0;
@@ -111,6 +135,54 @@ where
         Ok(())
     }
 
+    async fn read_trailers(&mut self) -> Result<(), Error> {
+        // The trailer section is a list of header lines terminated by an empty line
+        loop {
+            let line_start = self.trailers.len();
+            let mut keep = self.collect_trailers;
+            let mut line_len = 0;
+            let mut previous = 0;
+
+            loop {
+                let mut byte = 0;
+                self.raw_body
+                    .read_exact(core::slice::from_mut(&mut byte))
+                    .await
+                    .map_err(|e| Error::from(e).kind())?;
+                line_len += 1;
+
+                // Two bytes are kept free for the empty line that terminates the kept trailers
+                if keep && self.trailers.len() + 2 < MAX_TRAILERS_LEN {
+                    self.trailers.push(byte).unwrap();
+                } else if keep {
+                    warn!("Skipping trailer that does not fit in {} bytes", MAX_TRAILERS_LEN);
+                    self.trailers.truncate(line_start);
+                    keep = false;
+                }
+
+                if byte == b'\n' {
+                    break;
+                }
+                previous = byte;
+            }
+
+            if previous != b'\r' {
+                return Err(Error::Codec);
+            }
+
+            if line_len == 2 {
+                self.trailers.truncate(line_start);
+                if line_start > 0 {
+                    // Terminate the kept trailers, there is no section if there are none
+                    self.trailers.extend_from_slice(b"\r\n").unwrap();
+                }
+                return Ok(());
+            }
+
+            debug!("Read trailer line of {} bytes", line_len);
+        }
+    }
+
     /// Handles chunk boundary and returns the number of bytes in the current (or new) chunk.
     async fn handle_chunk_boundary(&mut self) -> Result<usize, Error> {
         match self.chunk_remaining {
@@ -128,8 +200,8 @@ where
         }
 
         if self.chunk_remaining == ChunkState::Empty {
-            // Read final chunk termination
-            self.read_chunk_end().await?;
+            // Read the trailers and the final chunk termination
+            self.read_trailers().await?;
         }
 
         Ok(self.chunk_remaining.len())
@@ -140,7 +212,7 @@ impl<'conn, 'buf, C> ChunkedBodyReader<BufferingReader<'conn, 'buf, C>>
 where
     C: Read + TryBufRead,
 {
-    pub(crate) async fn read_to_end(self) -> Result<&'buf mut [u8], Error> {
+    pub(crate) async fn read_to_end(self, max_body_len: Option<usize>) -> Result<&'buf mut [u8], PartialBody<'buf>> {
         let buffer = self.raw_body.buffer.buffer;
 
         // We reconstruct the reader to change the 'buf lifetime.
@@ -153,37 +225,91 @@ where
                 stream: self.raw_body.stream,
             },
             chunk_remaining: self.chunk_remaining,
+            collect_trailers: self.collect_trailers,
+            trailers: self.trailers,
         };
 
         let mut len = 0;
         while !reader.raw_body.buffer.buffer.is_empty() {
-            // Read some
-            let read = reader.fill_buf().await?.len();
-            len += read;
+            // Read the chunk header first, it may drain the preloaded bytes
+            if let Err(error) = reader.handle_chunk_boundary().await {
+                return Err(PartialBody {
+                    error,
+                    body: &buffer[..len],
+                });
+            }
 
-            // Make sure we don't erase the newly read data
-            let was_loaded = reader.raw_body.buffer.loaded;
-            let fake_loaded = read.min(was_loaded);
-            reader.raw_body.buffer.loaded = fake_loaded;
+            let read = if reader.raw_body.buffer.is_empty() {
+                // Nothing is preloaded, so read directly into the free part of the buffer.
+                // Data returned by fill_buf() would come from the buffer of the connection instead.
+                let free = core::mem::take(&mut reader.raw_body.buffer.buffer);
+                let read = match reader.read(free).await {
+                    Ok(read) => read,
+                    Err(error) => {
+                        return Err(PartialBody {
+                            error,
+                            body: &buffer[..len],
+                        })
+                    }
+                };
+                reader.raw_body.buffer.buffer = &mut free[read..];
+                read
+            } else {
+                // The preloaded data is already in place, fill_buf() returns it from the start of the buffer
+                let read = match reader.fill_buf().await {
+                    Ok(data) => data.len(),
+                    Err(error) => {
+                        return Err(PartialBody {
+                            error,
+                            body: &buffer[..len],
+                        })
+                    }
+                };
+
+                // Make sure we don't erase the newly read data
+                let was_loaded = reader.raw_body.buffer.loaded;
+                let fake_loaded = read.min(was_loaded);
+                reader.raw_body.buffer.loaded = fake_loaded;
+
+                // Consume the returned buffer
+                reader.consume(read);
+
+                // How many bytes were actually consumed from the preloaded buffer?
+                let consumed_from_buffer = fake_loaded - reader.raw_body.buffer.loaded;
+
+                // ... move the buffer by that many bytes to avoid overwriting in the next iteration.
+                reader.raw_body.buffer.loaded = was_loaded - consumed_from_buffer;
+                reader.raw_body.buffer.buffer =
+                    &mut core::mem::take(&mut reader.raw_body.buffer.buffer)[consumed_from_buffer..];
+                read
+            };
+
+            if read == 0 && !reader.is_done() {
+                // The connection was closed in the middle of a chunk
+                return Err(PartialBody {
+                    error: Error::ConnectionAborted,
+                    body: &buffer[..len],
+                });
+            }
+            len += read;
 
-            // Consume the returned buffer
-            reader.consume(read);
+            if max_body_len.is_some_and(|max| len > max) {
+                return Err(PartialBody {
+                    error: Error::BodyTooLarge,
+                    body: &buffer[..len],
+                });
+            }
 
             if reader.is_done() {
-                // If we're done, we don't care about the rest of the housekeeping.
                 break;
             }
-
-            // How many bytes were actually consumed from the preloaded buffer?
-            let consumed_from_buffer = fake_loaded - reader.raw_body.buffer.loaded;
-
-            // ... move the buffer by that many bytes to avoid overwriting in the next iteration.
-            reader.raw_body.buffer.loaded = was_loaded - consumed_from_buffer;
-            reader.raw_body.buffer.buffer = &mut reader.raw_body.buffer.buffer[consumed_from_buffer..];
         }
 
         if !reader.is_done() {
-            return Err(Error::BufferTooSmall);
+            return Err(PartialBody {
+                error: Error::BufferTooSmall,
+                body: &buffer[..len],
+            });
         }
 
         Ok(&mut buffer[..len])
@@ -206,7 +332,7 @@ where
             .raw_body
             .read(&mut buf[..max_len])
             .await
-            .map_err(|e| Error::Network(e.kind()))?;
+            .map_err(|e| Error::from(e.kind()))?;
 
         self.chunk_remaining.consume(len);
 
@@ -221,7 +347,7 @@ where
     async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
         let remaining = self.handle_chunk_boundary().await?;
 
-        let buf = self.raw_body.fill_buf().await.map_err(|e| Error::Network(e.kind()))?;
+        let buf = self.raw_body.fill_buf().await.map_err(|e| Error::from(e.kind()))?;
 
         let len = buf.len().min(remaining);
 
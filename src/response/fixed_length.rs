@@ -21,7 +21,13 @@ where
             return Ok(0);
         }
 
-        let read = self.raw_body.read(buf).await.map_err(|e| Error::Network(e.kind()))?;
+        // Never read past the body, as the connection may already contain the next response
+        let len = buf.len().min(self.remaining);
+        let read = self
+            .raw_body
+            .read(&mut buf[..len])
+            .await
+            .map_err(|e| Error::from(e.kind()))?;
         self.remaining -= read;
 
         Ok(read)
@@ -41,7 +47,7 @@ where
             .raw_body
             .fill_buf()
             .await
-            .map_err(|e| Error::Network(e.kind()))
+            .map_err(|e| Error::from(e.kind()))
             .map(|data| &data[..data.len().min(self.remaining)])?;
 
         if loaded.is_empty() {
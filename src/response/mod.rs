@@ -1,10 +1,12 @@
+use core::fmt::Write as _;
 use embedded_io::{Error as _, ErrorType};
-use embedded_io_async::{BufRead, Read};
+use embedded_io_async::{BufRead, Read, Write};
 use heapless::Vec;
+use nourl::Url;
 
-use crate::headers::{ContentType, KeepAlive, TransferEncoding};
+use crate::headers::{AuthChallenge, AuthChallenges, ContentType, KeepAlive, RetryAfter, TransferEncoding};
 use crate::reader::BufferingReader;
-use crate::request::Method;
+use crate::request::{HttpVersion, Method};
 use crate::response::chunked::ChunkedBodyReader;
 use crate::response::fixed_length::FixedLengthBodyReader;
 use crate::{Error, TryBufRead};
@@ -22,10 +24,12 @@ where
     conn: &'resp mut C,
     /// The method used to create the response.
     method: Method,
+    /// The HTTP version of the response.
+    version: HttpVersion,
     /// The HTTP response status code.
     pub status: Status,
     /// The HTTP response content type.
-    pub content_type: Option<ContentType>,
+    pub content_type: Option<ContentType<'static>>,
     /// The content length.
     pub content_length: Option<usize>,
     /// The transfer encoding.
@@ -34,7 +38,26 @@ where
     pub keep_alive: Option<KeepAlive>,
     header_buf: &'buf mut [u8],
     header_len: usize,
+    /// The location of each header in `header_buf`, so that headers are only parsed once.
+    header_index: Vec<HeaderSpan, MAX_HEADERS>,
     raw_body_read: usize,
+    max_body_len: Option<usize>,
+    /// Whether bytes following the end of the body were read together with the headers
+    has_trailing_bytes: bool,
+}
+
+/// The maximum number of headers in a response
+const MAX_HEADERS: usize = 64;
+
+/// The headers that are kept by [`Response::read_with_header_visitor`] as they are needed to read the body
+const KEPT_HEADERS: [&str; 4] = ["content-length", "transfer-encoding", "connection", "keep-alive"];
+
+/// The location of a header name and value in the header buffer
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+struct HeaderSpan {
+    name: (u16, u16),
+    value: (u16, u16),
 }
 
 impl<'resp, 'buf, C> Response<'resp, 'buf, C>
@@ -43,9 +66,44 @@ where
 {
     // Read at least the headers from the connection.
     pub async fn read(conn: &'resp mut C, method: Method, header_buf: &'buf mut [u8]) -> Result<Self, Error> {
-        let mut header_len = 0;
+        Self::read_with_options(conn, method, header_buf, ReadOptions::default()).await
+    }
+
+    /// Read at least the headers from the connection, using the provided options for parsing the response.
+    pub async fn read_with_options(
+        conn: &'resp mut C,
+        method: Method,
+        header_buf: &'buf mut [u8],
+        options: ReadOptions,
+    ) -> Result<Self, Error> {
+        let max_header_len = options.max_header_len(header_buf.len());
+        let header_len;
         let mut pos = 0;
-        while pos < header_buf.len() {
+        loop {
+            // Look for header end
+            let mut headers = [httparse::EMPTY_HEADER; 64];
+            let mut response = httparse::Response::new(&mut headers);
+            let parse_status = response
+                .parse(&header_buf[..pos])
+                .map_err(|e| parse_error(e, &header_buf[..pos]))?;
+            if let httparse::Status::Complete(len) = parse_status {
+                if !is_interim(response.code) {
+                    header_len = len;
+                    break;
+                }
+
+                // Skip the informational response and look for the final response after it
+                debug!("Skipping informational response {}", response.code.unwrap_or_default());
+                header_buf.copy_within(len..pos, 0);
+                pos -= len;
+                continue;
+            }
+
+            if pos >= max_header_len {
+                // Unable to completely read header within the limit
+                return Err(Error::HeadersTooLarge { needed: pos + 1 });
+            }
+
             let n = conn.read(&mut header_buf[pos..]).await.map_err(|e| {
                 /*warn!(
                     "error {:?}, but read data from socket:  {:?}",
@@ -60,28 +118,198 @@ where
             }
 
             pos += n;
+        }
+
+        if header_len > max_header_len {
+            return Err(Error::HeadersTooLarge { needed: header_len });
+        }
+
+        Self::parse(conn, method, header_buf, header_len, pos, options)
+    }
+
+    /// Read the headers from the connection, passing each header to `visitor` instead of keeping it in `buf`.
+    ///
+    /// The header section is parsed one line at a time, so `buf` only needs to hold the status line,
+    /// the longest header line and the headers that are needed to read the body, which allows reading
+    /// a large header section with a small buffer. Only the `Content-Length`, `Transfer-Encoding`,
+    /// `Connection` and `Keep-Alive` headers are kept, so the other headers are not available from
+    /// [`Response::header`] and [`Response::headers`]. The headers of skipped informational responses
+    /// are not visited.
+    pub async fn read_with_header_visitor<F>(
+        conn: &'resp mut C,
+        method: Method,
+        buf: &'buf mut [u8],
+        mut visitor: F,
+    ) -> Result<Self, Error>
+    where
+        F: FnMut(&str, &[u8]),
+    {
+        // The status line and the kept headers are at the start of the buffer, followed by the unparsed bytes
+        let mut kept = 0;
+        let mut pos = 0;
+        let mut code = None;
+        let header_len = loop {
+            let Some(line_end) = buf[kept..pos].iter().position(|&b| b == b'\n').map(|i| kept + i + 1) else {
+                if pos == buf.len() {
+                    return Err(Error::HeadersTooLarge { needed: pos + 1 });
+                }
+
+                let n = conn.read(&mut buf[pos..]).await.map_err(|e| e.kind())?;
+                if n == 0 {
+                    return Err(Error::ConnectionAborted);
+                }
+                pos += n;
+                continue;
+            };
+
+            let Some(line) = buf[kept..line_end].strip_suffix(b"\r\n") else {
+                return Err(Error::InvalidResponse);
+            };
+
+            let Some(status) = code else {
+                let mut response = httparse::Response::new(&mut []);
+                response
+                    .parse(&buf[kept..line_end])
+                    .map_err(|e| parse_error(e, &buf[kept..line_end]))?;
+                code = Some(
+                    response
+                        .code
+                        .ok_or_else(|| malformed_status_line(&buf[kept..line_end]))?,
+                );
+                kept = line_end;
+                continue;
+            };
+
+            if line.is_empty() {
+                if !is_interim(Some(status)) {
+                    break line_end;
+                }
+
+                // Skip the informational response and look for the final response after it
+                debug!("Skipping informational response {}", status);
+                buf.copy_within(line_end..pos, 0);
+                pos -= line_end;
+                kept = 0;
+                code = None;
+                continue;
+            }
+
+            let (name, value) = split_header_line(line)?;
+            if !is_interim(Some(status)) {
+                visitor(name, value);
+            }
+
+            if KEPT_HEADERS.iter().any(|kept| name.eq_ignore_ascii_case(kept)) {
+                kept = line_end;
+            } else {
+                buf.copy_within(line_end..pos, kept);
+                pos -= line_end - kept;
+            }
+        };
+
+        Self::parse(conn, method, buf, header_len, pos, ReadOptions::default())
+    }
+
+    /// Read the headers from a buffered connection without reading any of the body.
+    ///
+    /// This leaves everything after the headers in the connection, which is required
+    /// when multiple responses are read back-to-back from the same connection.
+    pub(crate) async fn read_buffered(
+        conn: &'resp mut C,
+        method: Method,
+        header_buf: &'buf mut [u8],
+    ) -> Result<Self, Error>
+    where
+        C: BufRead,
+    {
+        let mut header_len = 0;
+        let mut pos = 0;
+        while pos < header_buf.len() {
+            let available = conn.fill_buf().await.map_err(|e| e.kind())?;
+
+            if available.is_empty() {
+                return Err(Error::ConnectionAborted);
+            }
+
+            let n = available.len().min(header_buf.len() - pos);
+            header_buf[pos..pos + n].copy_from_slice(&available[..n]);
 
             // Look for header end
             let mut headers = [httparse::EMPTY_HEADER; 64];
             let mut response = httparse::Response::new(&mut headers);
-            let parse_status = response.parse(&header_buf[..pos]).map_err(|_| Error::Codec)?;
-            if parse_status.is_complete() {
-                header_len = parse_status.unwrap();
+            let parse_status = response
+                .parse(&header_buf[..pos + n])
+                .map_err(|e| parse_error(e, &header_buf[..pos + n]))?;
+            if let httparse::Status::Complete(len) = parse_status {
+                // Only consume the header bytes and leave the body in the connection
+                conn.consume(len - pos);
+                if is_interim(response.code) {
+                    // Skip the informational response and look for the final response after it
+                    debug!("Skipping informational response {}", response.code.unwrap_or_default());
+                    pos = 0;
+                    continue;
+                }
+                header_len = len;
+                pos = len;
                 break;
             }
+
+            conn.consume(n);
+            pos += n;
         }
 
         if header_len == 0 {
             // Unable to completely read header
-            return Err(Error::BufferTooSmall);
+            return Err(Error::HeadersTooLarge { needed: pos + 1 });
+        }
+
+        Self::parse(conn, method, header_buf, header_len, pos, ReadOptions::default())
+    }
+
+    fn parse(
+        conn: &'resp mut C,
+        method: Method,
+        header_buf: &'buf mut [u8],
+        header_len: usize,
+        pos: usize,
+        options: ReadOptions,
+    ) -> Result<Self, Error> {
+        if header_len > u16::MAX as usize {
+            // The header index only supports offsets that fit in 16 bits
+            return Err(Error::HeadersTooLarge { needed: header_len });
+        }
+
+        if !options.lenient_parsing && has_bare_lf(&header_buf[..header_len]) {
+            return Err(Error::InvalidResponse);
         }
 
         // Parse status and known headers
-        let mut headers = [httparse::EMPTY_HEADER; 64];
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
         let mut response = httparse::Response::new(&mut headers);
         response.parse(&header_buf[..header_len]).unwrap();
 
+        let buf_start = header_buf.as_ptr() as usize;
+        let span = |slice: &[u8]| {
+            let start = (slice.as_ptr() as usize).wrapping_sub(buf_start);
+            if slice.is_empty() || start >= header_len {
+                return (0, 0);
+            }
+            (start as u16, (start + slice.len()) as u16)
+        };
+        let mut header_index = Vec::new();
+        for header in response.headers.iter() {
+            // The index has the same capacity as the parsed header array
+            let _ = header_index.push(HeaderSpan {
+                name: span(header.name.as_bytes()),
+                value: span(header.value),
+            });
+        }
+
         let status = response.code.unwrap().into();
+        let version = match response.version {
+            Some(0) => HttpVersion::Http10,
+            _ => HttpVersion::Http11,
+        };
         let mut content_type = None;
         let mut content_length = None;
         let mut transfer_encoding = Vec::new();
@@ -89,14 +317,22 @@ where
 
         for header in response.headers {
             if header.name.eq_ignore_ascii_case("content-type") {
-                content_type.replace(header.value.into());
+                // Content types that are not known by this crate are reported as `application/octet-stream`
+                let known = core::str::from_utf8(header.value)
+                    .ok()
+                    .and_then(|value| ContentType::from_known(value.trim()));
+                content_type.replace(known.unwrap_or(ContentType::ApplicationOctetStream));
             } else if header.name.eq_ignore_ascii_case("content-length") {
-                content_length = Some(
-                    core::str::from_utf8(header.value)
-                        .map_err(|_| Error::Codec)?
-                        .parse::<usize>()
-                        .map_err(|_| Error::Codec)?,
-                );
+                let value = core::str::from_utf8(header.value)
+                    .map_err(|_| Error::Codec)?
+                    .parse::<usize>()
+                    .map_err(|_| Error::Codec)?;
+                if content_length.is_some_and(|content_length| content_length != value) {
+                    // https://www.rfc-editor.org/rfc/rfc9112#section-6.3 pt. 5: the body length is ambiguous
+                    warn!("Response has conflicting Content-Length headers");
+                    return Err(Error::InvalidResponse);
+                }
+                content_length = Some(value);
             } else if header.name.eq_ignore_ascii_case("transfer-encoding") {
                 transfer_encoding
                     .push(header.value.try_into().map_err(|_| Error::Codec)?)
@@ -106,19 +342,22 @@ where
             }
         }
 
-        // The number of bytes that we have read into the body part of the response
-        let raw_body_read = pos - header_len;
-
-        if let Some(content_length) = content_length {
-            if content_length < raw_body_read {
-                // We have more into the body then what is specified in content_length
-                return Err(Error::Codec);
+        if content_length.is_some() && transfer_encoding.contains(&TransferEncoding::Chunked) {
+            // https://www.rfc-editor.org/rfc/rfc7230#section-3.3.3 pt. 3: Transfer-Encoding overrides Content-Length
+            if options.strict {
+                return Err(Error::InvalidResponse);
             }
+            warn!("Response has both Content-Length and chunked Transfer-Encoding, ignoring Content-Length");
+            content_length = None;
         }
 
-        Ok(Response {
+        // The number of bytes that we have read into the body part of the response
+        let raw_body_read = pos - header_len;
+
+        let mut response = Response {
             conn,
             method,
+            version,
             status,
             content_type,
             content_length,
@@ -126,380 +365,1901 @@ where
             keep_alive,
             header_buf,
             header_len,
+            header_index,
             raw_body_read,
-        })
+            max_body_len: None,
+            has_trailing_bytes: false,
+        };
+
+        let body_len = match response.reader_hint() {
+            ReaderHint::Empty => Some(0),
+            ReaderHint::FixedLength(content_length) => Some(content_length),
+            ReaderHint::Chunked { .. } | ReaderHint::ToEnd => None,
+        };
+        if let Some(body_len) = body_len.filter(|&body_len| raw_body_read > body_len && !response.is_tunnel()) {
+            // The server sent more than the body, which would corrupt the next response on the connection
+            if options.strict {
+                return Err(Error::InvalidResponse);
+            }
+            warn!(
+                "Response has {} unexpected bytes after the body",
+                raw_body_read - body_len
+            );
+            response.raw_body_read = body_len;
+            response.has_trailing_bytes = true;
+        }
+
+        Ok(response)
+    }
+
+    /// Limit the number of body bytes that may be read from the response.
+    ///
+    /// Reading more than `max_body_len` bytes from the body returns [`Error::BodyTooLarge`].
+    /// The limit is checked as the body is read, so a server streaming a never ending
+    /// chunked or connection-delimited body is detected as soon as the limit is passed.
+    pub fn max_body_len(mut self, max_body_len: usize) -> Self {
+        self.max_body_len = Some(max_body_len);
+        self
+    }
+
+    /// Get the response status.
+    ///
+    /// The status and headers are available as soon as the response is read, before the body is touched.
+    /// Reading the response only reads from the connection until the end of the header section is received,
+    /// and body bytes received together with the headers are kept, see [`Response::body_buffered`].
+    /// The body is only read through [`Response::body`], so it can be read or discarded based on the status.
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Get the HTTP version of the response.
+    ///
+    /// HTTP/1.0 servers close the connection after the response unless it has `Connection: keep-alive`,
+    /// see [`Response::is_connection_reusable`].
+    pub fn version(&self) -> HttpVersion {
+        self.version
     }
 
     /// Get the response headers
     pub fn headers(&self) -> HeaderIterator {
-        let mut iterator = HeaderIterator(0, [httparse::EMPTY_HEADER; 64]);
-        let mut response = httparse::Response::new(&mut iterator.1);
-        response.parse(&self.header_buf[..self.header_len]).unwrap();
+        let mut iterator = HeaderIterator(0, [httparse::EMPTY_HEADER; MAX_HEADERS]);
+        for (header, span) in iterator.1.iter_mut().zip(self.header_index.iter()) {
+            let (name, value) = self.header_at(span);
+            *header = httparse::Header { name, value };
+        }
 
         iterator
     }
 
-    /// Get the response body
-    pub fn body(self) -> ResponseBody<'resp, 'buf, C> {
-        let reader_hint = if self.method == Method::HEAD {
-            // Head requests does not have a body so we return an empty reader
-            ReaderHint::Empty
-        } else if let Some(content_length) = self.content_length {
-            ReaderHint::FixedLength(content_length)
-        } else if self.transfer_encoding.contains(&TransferEncoding::Chunked) {
-            ReaderHint::Chunked
-        } else {
-            ReaderHint::ToEnd
-        };
-
-        // Move the body part of the bytes in the header buffer to the beginning of the buffer.
-        self.header_buf
-            .copy_within(self.header_len..self.header_len + self.raw_body_read, 0);
+    /// Get the value of the first header with the given name.
+    ///
+    /// The headers are parsed once when the response is read, so looking up headers does not parse them again.
+    pub fn header(&self, name: &str) -> Option<&[u8]> {
+        self.header_index
+            .iter()
+            .map(|span| self.header_at(span))
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
 
-        ResponseBody {
-            conn: self.conn,
-            reader_hint,
-            body_buf: self.header_buf,
-            raw_body_read: self.raw_body_read,
-        }
+    fn header_at(&self, span: &HeaderSpan) -> (&str, &[u8]) {
+        let slice = |(start, end): (u16, u16)| &self.header_buf[start as usize..end as usize];
+        // Header names are validated as tokens by the parser, so they are always valid UTF-8
+        let name = core::str::from_utf8(slice(span.name)).unwrap_or_default();
+        (name, slice(span.value))
     }
-}
 
-pub struct HeaderIterator<'a>(usize, [httparse::Header<'a>; 64]);
+    /// Get the value of the first header with the given name as a string
+    fn header_str(&self, name: &str) -> Option<&str> {
+        self.header(name).and_then(|value| core::str::from_utf8(value).ok())
+    }
 
-impl<'a> Iterator for HeaderIterator<'a> {
-    type Item = (&'a str, &'a [u8]);
+    /// Get the file name from the `Content-Disposition` header, decoded into `buf`.
+    ///
+    /// The RFC 5987 `filename*=` form is preferred over `filename=` if both are present.
+    /// Only the UTF-8 charset is supported for `filename*=`, other charsets fall back to `filename=`.
+    /// Returns `Ok(None)` if there is no file name.
+    pub fn content_disposition_filename<'b>(&self, buf: &'b mut [u8]) -> Result<Option<&'b str>, Error> {
+        let Some(value) = self.header_str("content-disposition") else {
+            return Ok(None);
+        };
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let result = self.1.get(self.0);
+        let mut filename = None;
+        let mut extended_filename = None;
+        for (name, value) in ParameterIterator(value) {
+            if name.eq_ignore_ascii_case("filename") {
+                filename = Some(value);
+            } else if name.eq_ignore_ascii_case("filename*") {
+                // charset'language'percent-encoded-value
+                let mut parts = value.splitn(3, '\'');
+                if let (Some(charset), Some(_), Some(encoded)) = (parts.next(), parts.next(), parts.next()) {
+                    if charset.eq_ignore_ascii_case("utf-8") {
+                        extended_filename = Some(encoded);
+                    }
+                }
+            }
+        }
 
-        self.0 += 1;
+        let decoded = if let Some(encoded) = extended_filename {
+            crate::percent::decode(encoded.as_bytes(), buf)?
+        } else if let Some(quoted) = filename {
+            unquote(quoted, buf)?
+        } else {
+            return Ok(None);
+        };
 
-        result.map(|h| (h.name, h.value))
+        Ok(Some(core::str::from_utf8(decoded)?))
     }
-}
 
-/// Response body
-///
-/// This type contains the original header buffer provided to `read_headers`,
-/// now renamed to `body_buf`, the number of read body bytes that are available
-/// in `body_buf`, and a reader to be used for reading the remaining body.
-pub struct ResponseBody<'resp, 'buf, C>
-where
-    C: Read,
-{
-    conn: &'resp mut C,
-    reader_hint: ReaderHint,
-    /// The number of raw bytes read from the body and available in the beginning of `body_buf`.
-    raw_body_read: usize,
-    /// The buffer initially provided to read the header.
-    pub body_buf: &'buf mut [u8],
-}
+    /// Get the methods listed in the `Allow` header, typically sent in response to an OPTIONS request.
+    ///
+    /// Unknown methods are skipped.
+    pub fn allowed_methods(&self) -> impl Iterator<Item = Method> + '_ {
+        self.header_str("allow")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|method| Method::from_str(method.trim()))
+    }
 
-#[derive(Clone, Copy)]
-enum ReaderHint {
-    Empty,
-    FixedLength(usize),
-    Chunked,
-    ToEnd, // https://www.rfc-editor.org/rfc/rfc7230#section-3.3.3 pt. 7: Until end of connection
-}
+    /// Whether the `Connection` header contains the given token
+    fn has_connection_token(&self, token: &str) -> bool {
+        self.header_str("connection")
+            .is_some_and(|value| value.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+    }
 
-impl ReaderHint {
-    fn reader<R: Read>(self, raw_body: R) -> BodyReader<R> {
-        match self {
-            ReaderHint::Empty => BodyReader::Empty,
-            ReaderHint::FixedLength(content_length) => BodyReader::FixedLength(FixedLengthBodyReader {
-                raw_body,
-                remaining: content_length,
-            }),
-            ReaderHint::Chunked => BodyReader::Chunked(ChunkedBodyReader::new(raw_body)),
-            ReaderHint::ToEnd => BodyReader::ToEnd(raw_body),
-        }
+    /// Whether the server will close the connection after this response
+    pub(crate) fn is_connection_close(&self) -> bool {
+        self.has_connection_token("close")
     }
-}
 
-impl<'resp, 'buf, C> ResponseBody<'resp, 'buf, C>
-where
-    C: Read,
-{
-    pub fn reader(self) -> BodyReader<BufferingReader<'resp, 'buf, C>> {
-        let raw_body = BufferingReader::new(self.body_buf, self.raw_body_read, self.conn);
+    /// Whether the headers of the response allow the connection to be reused for another request
+    pub(crate) fn allows_connection_reuse(&self) -> bool {
+        let keep_alive = match self.version {
+            HttpVersion::Http11 => !self.has_connection_token("close"),
+            HttpVersion::Http10 => self.has_connection_token("keep-alive"),
+        };
 
-        self.reader_hint.reader(raw_body)
+        // A body delimited by the end of the connection can never be followed by another response
+        keep_alive && !self.has_trailing_bytes && !self.is_tunnel() && !matches!(self.reader_hint(), ReaderHint::ToEnd)
     }
-}
-
-impl<'resp, 'buf, C> ResponseBody<'resp, 'buf, C>
-where
-    C: Read + TryBufRead,
-{
-    /// Read the entire body into the buffer originally provided [`Response::read()`].
-    /// This requires that this original buffer is large enough to contain the entire body.
-    pub async fn read_to_end(mut self) -> Result<&'buf mut [u8], Error> {
-        match self.reader_hint {
-            ReaderHint::Empty => Ok(&mut []),
-            ReaderHint::FixedLength(content_length) => {
-                let read = BodyReader::FixedLength(FixedLengthBodyReader {
-                    raw_body: self.conn,
-                    remaining: content_length - self.raw_body_read,
-                })
-                .read_to_end(&mut self.body_buf[self.raw_body_read..])
-                .await?;
 
-                Ok(&mut self.body_buf[..read + self.raw_body_read])
-            }
-            ReaderHint::Chunked => {
-                let raw_body = BufferingReader::new(self.body_buf, self.raw_body_read, self.conn);
-                ChunkedBodyReader::new(raw_body).read_to_end().await
-            }
-            ReaderHint::ToEnd => {
-                let read = BodyReader::ToEnd(&mut self.conn)
-                    .read_to_end(&mut self.body_buf[self.raw_body_read..])
-                    .await?;
+    /// Whether the response establishes a tunnel, after which the connection is no longer used for HTTP
+    fn is_tunnel(&self) -> bool {
+        self.method == Method::CONNECT && self.status.is_successful()
+    }
 
-                Ok(&mut self.body_buf[..read + self.raw_body_read])
-            }
+    /// Get the connection of a successful response to a `CONNECT` request, which is now a tunnel to the target.
+    ///
+    /// The bytes that the server sent through the tunnel directly after the response, and that were read
+    /// together with the headers, are returned with the connection. Returns `None` for other responses.
+    pub fn into_tunnel(self) -> Option<(&'resp mut C, &'buf [u8])> {
+        if !self.is_tunnel() {
+            return None;
         }
+        let header_buf: &'buf [u8] = self.header_buf;
+        Some((
+            self.conn,
+            &header_buf[self.header_len..self.header_len + self.raw_body_read],
+        ))
     }
 
-    /// Discard the entire body
+    /// Check whether the connection can be reused for another request.
     ///
-    /// Returns the number of discarded body bytes
-    pub async fn discard(self) -> Result<usize, Error> {
-        self.reader().discard().await
+    /// This is the case if the response is HTTP/1.1 without `Connection: close` or HTTP/1.0 with
+    /// `Connection: keep-alive`, and the response has no body left to read.
+    /// Use [`BodyReader::is_connection_reusable`] to check after the body is read.
+    pub fn is_connection_reusable(&self) -> bool {
+        let no_body = match self.reader_hint() {
+            ReaderHint::Empty => true,
+            ReaderHint::FixedLength(content_length) => content_length == 0,
+            _ => false,
+        };
+        no_body && self.allows_connection_reuse()
     }
-}
 
-/// A body reader
-pub enum BodyReader<B> {
-    Empty,
-    FixedLength(FixedLengthBodyReader<B>),
-    Chunked(ChunkedBodyReader<B>),
-    ToEnd(B),
-}
+    /// Get the `timeout` and `max` parameters of the `Keep-Alive` header, if any.
+    ///
+    /// A connection pool can use them to close an idle connection before the server does.
+    /// Parameters that are missing or cannot be parsed are `None`.
+    pub fn keep_alive_params(&self) -> Option<KeepAlive> {
+        self.keep_alive
+    }
 
-impl<B> BodyReader<B>
-where
-    B: Read,
-{
-    fn is_done(&self) -> bool {
-        match self {
-            BodyReader::Empty => true,
-            BodyReader::FixedLength(reader) => reader.remaining == 0,
-            BodyReader::Chunked(reader) => reader.is_done(),
-            BodyReader::ToEnd(_) => false,
-        }
+    /// Get the value of the `Retry-After` header, typically sent with `429 Too Many Requests`
+    /// or `503 Service Unavailable` responses.
+    ///
+    /// Only the delay in seconds and the preferred IMF-fixdate format of the date are supported.
+    /// Returns `None` if the header is missing or cannot be parsed.
+    pub fn retry_after(&self) -> Option<RetryAfter> {
+        self.header_str("retry-after")?.as_bytes().try_into().ok()
     }
 
-    /// Read the entire body
-    pub async fn read_to_end(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
-        let mut len = 0;
-        while len < buf.len() {
-            match self.read(&mut buf[len..]).await {
-                Ok(0) => break,
-                Ok(n) => len += n,
-                Err(e) => return Err(e),
-            }
-        }
+    /// Get the target of the `Location` header, typically sent with `3xx` redirect responses.
+    ///
+    /// Relative references, such as `/other` or `../other`, are resolved against `base`, which should be
+    /// the URL of the request. The resolved URL is written into `buf`.
+    /// Returns `Ok(None)` if the response does not have a `Location` header.
+    pub fn location<'b>(&self, base: &Url<'_>, buf: &'b mut [u8]) -> Result<Option<Url<'b>>, Error> {
+        let Some(location) = self.header_str("location") else {
+            return Ok(None);
+        };
 
-        if !self.is_done() {
-            let more = match self {
-                BodyReader::FixedLength(reader) => {
-                    warn!("FixedLength: {} bytes remained", reader.remaining);
-                    true
-                }
-                BodyReader::ToEnd(reader) if len == buf.len() => {
-                    warn!("ToEnd: Buffer full, waiting to see if there is unread data.");
+        let resolved = resolve_reference(base, location.trim(), buf)?;
+        Ok(Some(Url::parse(resolved)?))
+    }
 
-                    let mut b = [0];
-                    matches!(reader.read(&mut b).await, Ok(1))
-                }
+    /// Get the first challenge of the `WWW-Authenticate` header, typically sent with `401 Unauthorized` responses.
+    pub fn auth_challenge(&self) -> Option<AuthChallenge<'_>> {
+        self.auth_challenges().next()
+    }
 
-                BodyReader::ToEnd(_) => false,
-                _ => true,
-            };
+    /// Get all challenges of the `WWW-Authenticate` headers.
+    pub fn auth_challenges(&self) -> impl Iterator<Item = AuthChallenge<'_>> + '_ {
+        self.header_index
+            .iter()
+            .map(|span| self.header_at(span))
+            .filter(|(name, _)| name.eq_ignore_ascii_case("www-authenticate"))
+            .filter_map(|(_, value)| core::str::from_utf8(value).ok())
+            .flat_map(AuthChallenges::new)
+    }
 
-            if more {
-                return Err(Error::BufferTooSmall);
-            }
-        }
+    /// Get the entity tag of the response from the `ETag` header, including its quotes and any `W/` prefix.
+    ///
+    /// The entity tag can be passed to [`RequestBuilder::if_match`](crate::request::RequestBuilder::if_match)
+    /// to only update the resource if it has not changed since.
+    pub fn etag(&self) -> Option<&str> {
+        self.header_str("etag").map(str::trim)
+    }
 
-        Ok(len)
+    /// Get the content type of the response.
+    ///
+    /// Any parameters, such as `charset`, are ignored. Use [`Response::charset()`] to get the charset.
+    /// Returns `None` if the response does not have a `Content-Type` header.
+    /// Unlike the `content_type` field, which reports unknown and parameterized content types as
+    /// `application/octet-stream`, unknown media types are returned as [`ContentType::Other`].
+    pub fn content_type(&self) -> Option<ContentType<'_>> {
+        let value = self.header_str("content-type")?;
+        let media_type = value.split(';').next().unwrap_or_default();
+        Some(ContentType::from_str(media_type))
     }
 
-    async fn discard(&mut self) -> Result<usize, Error> {
-        let mut body_len = 0;
+    /// Get the `charset` parameter of the `Content-Type` header, if any.
+    pub fn charset(&self) -> Option<&str> {
+        let value = self.header_str("content-type")?;
+        value.split(';').skip(1).find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            key.trim()
+                .eq_ignore_ascii_case("charset")
+                .then(|| value.trim().trim_matches('"'))
+        })
+    }
+
+    /// Copy the entire response body into `writer`.
+    ///
+    /// The decoded body is copied through a small scratch buffer, which allows writing a large body
+    /// to a sink such as flash without buffering all of it in memory.
+    /// Returns the number of body bytes written.
+    pub async fn body_to_writer<W: Write>(self, writer: &mut W) -> Result<usize, Error> {
+        let mut reader = self.body().reader();
         let mut buf = [0; 128];
+        let mut written = 0;
         loop {
-            let buf = self.read(&mut buf).await?;
-            if buf == 0 {
+            let len = reader.read(&mut buf).await?;
+            if len == 0 {
                 break;
             }
-            body_len += buf;
+            writer.write_all(&buf[..len]).await.map_err(|e| e.kind())?;
+            written += len;
         }
 
-        Ok(body_len)
+        writer.flush().await.map_err(|e| e.kind())?;
+        Ok(written)
     }
-}
 
-impl<B> ErrorType for BodyReader<B> {
-    type Error = Error;
-}
+    /// Read the entire response body into a vector with a capacity of `N` bytes.
+    ///
+    /// Returns [`Error::BodyTooLarge`] if the decoded body is longer than `N` bytes.
+    pub async fn body_to_vec<const N: usize>(self) -> Result<Vec<u8, N>, Error> {
+        let mut vec = Vec::new();
+        vec.resize(N, 0).unwrap();
+
+        let mut reader = self.body().reader();
+        let len = reader.read_to_end(&mut vec).await.map_err(|e| match e {
+            Error::BufferTooSmall => Error::BodyTooLarge,
+            e => e,
+        })?;
+
+        vec.truncate(len);
+        Ok(vec)
+    }
 
-impl<B> Read for BodyReader<B>
-where
-    B: Read,
-{
-    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
-        match self {
-            BodyReader::Empty => Ok(0),
-            BodyReader::FixedLength(reader) => reader.read(buf).await,
-            BodyReader::Chunked(reader) => reader.read(buf).await,
-            BodyReader::ToEnd(conn) => conn.read(buf).await.map_err(|e| Error::Network(e.kind())),
+    /// Copy the entire response body into `writer` and verify it against the SHA-256 digest in the trailers.
+    ///
+    /// The body is hashed as it is streamed, and the hash is compared with the `sha-256` entry of the `Digest`
+    /// trailer that is received after the last chunk, such as `Digest: sha-256=<base64 encoded hash>`.
+    /// The response must advertise the trailer in a `Trailer` header.
+    /// Returns [`Error::IntegrityCheckFailed`] if the digests differ or if the response has no such trailer,
+    /// in which case the body is already written and should be discarded by the caller.
+    /// Returns the number of body bytes written.
+    #[cfg(feature = "digest")]
+    pub async fn verify_trailer_digest<W: Write>(self, writer: &mut W) -> Result<usize, Error> {
+        use sha2::{Digest, Sha256};
+
+        let mut reader = self.body().reader();
+        let mut hasher = Sha256::new();
+        let mut buf = [0; 128];
+        let mut written = 0;
+        loop {
+            let len = reader.read(&mut buf).await?;
+            if len == 0 {
+                break;
+            }
+            hasher.update(&buf[..len]);
+            writer.write_all(&buf[..len]).await.map_err(|e| e.kind())?;
+            written += len;
+        }
+        writer.flush().await.map_err(|e| e.kind())?;
+
+        let expected = reader
+            .trailers()
+            .into_iter()
+            .flatten()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("digest"))
+            .find_map(|(_, value)| sha256_digest(value));
+        match expected {
+            Some(expected) if expected == hasher.finalize().as_slice() => Ok(written),
+            Some(_) => {
+                warn!("The body does not match the digest in the trailers");
+                Err(Error::IntegrityCheckFailed)
+            }
+            None => {
+                warn!("The response has no SHA-256 digest in the trailers");
+                Err(Error::IntegrityCheckFailed)
+            }
         }
     }
-}
 
-impl<B> BufRead for BodyReader<B>
-where
-    B: BufRead + Read,
-{
-    async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
-        match self {
-            BodyReader::Empty => Ok(&[]),
-            BodyReader::FixedLength(reader) => reader.fill_buf().await,
-            BodyReader::Chunked(reader) => reader.fill_buf().await,
-            BodyReader::ToEnd(conn) => conn.fill_buf().await.map_err(|e| Error::Network(e.kind())),
+    /// Read and discard the entire response body, returning the number of discarded body bytes.
+    ///
+    /// The body is read through a small scratch buffer, so no buffer is needed for it. This leaves
+    /// the connection ready for the next response if it is reusable, see [`Response::is_connection_reusable`].
+    /// The limit set with [`Response::max_body_len`] is enforced.
+    pub async fn consume_body(self) -> Result<usize, Error> {
+        self.body().reader().discard().await
+    }
+
+    /// Process the response body with `handler`, and discard the part of the body that it did not read.
+    ///
+    /// This allows streaming the body without keeping the [`ResponseBody`] and [`BodyReader`] around.
+    /// After the handler returns successfully, the rest of the body is read and discarded like
+    /// [`Response::consume_body`], which leaves the connection ready for the next response if it is reusable.
+    /// If the handler fails, its error is returned without reading the rest of the body.
+    pub async fn with_body<H: BodyHandler>(self, handler: H) -> Result<H::Output, Error> {
+        let mut reader = self.body().reader();
+        let output = handler.handle(&mut reader).await?;
+        reader.discard().await?;
+        Ok(output)
+    }
+
+    /// Stop using the response, returning whether the connection can be reused for another request.
+    ///
+    /// A remaining body of at most `max_drain` bytes is read and discarded, which leaves the connection
+    /// ready for the next response. A longer body is not read, and `false` is returned as the connection
+    /// is then in an unknown state and should be closed by dropping it.
+    pub async fn abort(self, max_drain: usize) -> Result<bool, Error> {
+        if !self.allows_connection_reuse() {
+            return Ok(false);
+        }
+        if let ReaderHint::FixedLength(content_length) = self.reader_hint() {
+            if content_length - self.raw_body_read > max_drain {
+                return Ok(false);
+            }
+        }
+
+        let mut reader = self.body().reader();
+        let mut buf = [0; 128];
+        let mut drained = 0;
+        while !reader.is_done() && drained <= max_drain {
+            // Read at most one byte more than allowed to find out if the body is too long
+            let len = buf.len().min(max_drain + 1 - drained);
+            match reader.read(&mut buf[..len]).await? {
+                0 => break,
+                n => drained += n,
+            }
         }
+
+        Ok(reader.is_connection_reusable())
     }
 
-    fn consume(&mut self, amt: usize) {
-        match self {
-            BodyReader::Empty => {}
-            BodyReader::FixedLength(reader) => reader.consume(amt),
-            BodyReader::Chunked(reader) => reader.consume(amt),
-            BodyReader::ToEnd(conn) => conn.consume(amt),
+    /// Get the body bytes that were read into the buffer together with the headers.
+    ///
+    /// The bytes directly follow the header section in the buffer passed to [`Response::read`].
+    /// They are the raw bytes as received, so for a chunked response they include the chunk framing.
+    /// If the entire body is buffered, for example when it is as long as [`Response::content_length`],
+    /// it can be used without copying it. Otherwise, use [`Response::body`] to read the rest of it.
+    pub fn body_buffered(&self) -> &[u8] {
+        &self.header_buf[self.header_len..self.header_len + self.raw_body_read]
+    }
+
+    /// Get the response body
+    pub fn body(self) -> ResponseBody<'resp, 'buf, C> {
+        let reader_hint = self.reader_hint();
+        let connection_reusable = self.allows_connection_reuse();
+
+        // Move the body part of the bytes in the header buffer to the beginning of the buffer.
+        self.header_buf
+            .copy_within(self.header_len..self.header_len + self.raw_body_read, 0);
+
+        ResponseBody {
+            conn: self.conn,
+            reader_hint,
+            body_buf: self.header_buf,
+            raw_body_read: self.raw_body_read,
+            max_body_len: self.max_body_len,
+            connection_reusable,
+        }
+    }
+
+    fn reader_hint(&self) -> ReaderHint {
+        if self.method == Method::HEAD || self.is_tunnel() {
+            // Head requests does not have a body so we return an empty reader,
+            // and the connection is a tunnel after a successful response to a CONNECT request
+            ReaderHint::Empty
+        } else if matches!(self.status, Status::NoContent | Status::NotModified) {
+            // These responses never have a body, even without a length, so the connection is not read until closed
+            ReaderHint::Empty
+        } else if let Some(content_length) = self.content_length {
+            ReaderHint::FixedLength(content_length)
+        } else if self.transfer_encoding.contains(&TransferEncoding::Chunked) {
+            ReaderHint::Chunked {
+                trailers: self.header("trailer").is_some(),
+            }
+        } else {
+            ReaderHint::ToEnd
         }
     }
 }
 
-/// HTTP status types
-#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
-#[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub enum Status {
-    Ok = 200,
-    Created = 201,
-    Accepted = 202,
-    NoContent = 204,
-    PartialContent = 206,
-    MovedPermanently = 301,
-    Found = 302,
-    SeeOther = 303,
-    NotModified = 304,
-    TemporaryRedirect = 307,
-    PermanentRedirect = 308,
-    BadRequest = 400,
-    Unauthorized = 401,
-    Forbidden = 403,
-    NotFound = 404,
-    MethodNotAllowed = 405,
-    Conflict = 409,
-    UnsupportedMediaType = 415,
-    RangeNotSatisfiable = 416,
-    TooManyRequests = 429,
-    InternalServerError = 500,
-    BadGateway = 502,
-    ServiceUnavailable = 503,
-    GatewayTimeout = 504,
-    Unknown = 0,
+/// Map an error from parsing the response head, logging the start of the malformed line.
+fn parse_error(error: httparse::Error, head: &[u8]) -> Error {
+    match error {
+        httparse::Error::Version | httparse::Error::Status => malformed_status_line(head),
+        httparse::Error::TooManyHeaders => {
+            warn!("Response has too many headers");
+            Error::Codec
+        }
+        _ => {
+            match malformed_header_line(head) {
+                Some((offset, line)) => warn!(
+                    "Malformed header line at byte {}: {:?}",
+                    offset,
+                    &line[..line.len().min(32)]
+                ),
+                None => warn!("Malformed header section: {:?}", &head[..head.len().min(32)]),
+            }
+            Error::Codec
+        }
+    }
 }
 
-impl Status {
-    pub fn is_informational(&self) -> bool {
-        let status = *self as u16;
-        (100..=199).contains(&status)
+/// Log the start of the malformed status line at the start of `head`.
+fn malformed_status_line(head: &[u8]) -> Error {
+    let line = head.split(|&b| b == b'\n').next().unwrap_or_default();
+    warn!("Malformed status line: {:?}", &line[..line.len().min(32)]);
+    Error::MalformedStatusLine
+}
+
+/// Find the first malformed header line after the status line, returning its offset in `head` and its bytes.
+fn malformed_header_line(head: &[u8]) -> Option<(usize, &[u8])> {
+    let mut lines = head.split_inclusive(|&b| b == b'\n');
+    let mut offset = lines.next()?.len();
+    for line in lines {
+        let content = line.strip_suffix(b"\n").unwrap_or(line);
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+        if content.is_empty() {
+            return None;
+        }
+
+        let valid = split_header_line(content)
+            .is_ok_and(|(_, value)| !value.iter().any(|&b| b.is_ascii_control() && b != b'\t'));
+        if !valid {
+            return Some((offset, content));
+        }
+        offset += line.len();
     }
+    None
+}
 
-    pub fn is_successful(&self) -> bool {
-        let status = *self as u16;
-        (200..=299).contains(&status)
+/// Resolve a URI reference against `base` as specified by RFC 3986, section 5.2.
+fn resolve_reference<'b>(base: &Url<'_>, reference: &str, buf: &'b mut [u8]) -> Result<&'b str, Error> {
+    let mut len = 0;
+    let push = |buf: &mut [u8], len: &mut usize, s: &str| -> Result<(), Error> {
+        buf.get_mut(*len..*len + s.len())
+            .ok_or(Error::BufferTooSmall)?
+            .copy_from_slice(s.as_bytes());
+        *len += s.len();
+        Ok(())
+    };
+
+    if has_scheme(reference) {
+        push(buf, &mut len, reference)?;
+    } else if reference.starts_with("//") {
+        push(buf, &mut len, base.scheme().as_str())?;
+        push(buf, &mut len, ":")?;
+        push(buf, &mut len, reference)?;
+    } else {
+        push(buf, &mut len, base.scheme().as_str())?;
+        push(buf, &mut len, "://")?;
+        if base.host().contains(':') {
+            // The url parser removes the brackets around an IPv6 address, which must be restored
+            push(buf, &mut len, "[")?;
+            push(buf, &mut len, base.host())?;
+            if let Some(scope_id) = base.scope_id() {
+                let mut scope_buf: heapless::String<11> = heapless::String::new();
+                write!(scope_buf, "%{}", scope_id).map_err(|_| Error::Codec)?;
+                push(buf, &mut len, scope_buf.as_str())?;
+            }
+            push(buf, &mut len, "]")?;
+        } else {
+            push(buf, &mut len, base.host())?;
+        }
+        if let Some(port) = base.port() {
+            let mut port_buf = [0; 6];
+            push(buf, &mut len, format_port(port, &mut port_buf))?;
+        }
+
+        let (base_path, _) = split_query(base.path());
+        let (path, query) = split_query(reference);
+        let path_start = len;
+        if path.starts_with('/') {
+            push(buf, &mut len, path)?;
+        } else if path.is_empty() {
+            push(buf, &mut len, if query.is_empty() { base.path() } else { base_path })?;
+        } else {
+            let directory = base_path.rfind('/').map_or("/", |index| &base_path[..=index]);
+            push(buf, &mut len, directory)?;
+            push(buf, &mut len, path)?;
+        }
+
+        if !path.is_empty() {
+            len = remove_dot_segments(buf, path_start, len);
+        }
+        push(buf, &mut len, query)?;
     }
 
-    pub fn is_redirection(&self) -> bool {
-        let status = *self as u16;
-        (300..=399).contains(&status)
+    Ok(core::str::from_utf8(&buf[..len])?)
+}
+
+/// Check whether a URI reference starts with a scheme, which makes it an absolute URI (RFC 3986, section 3.1).
+fn has_scheme(reference: &str) -> bool {
+    match reference.find([':', '/', '?', '#']) {
+        Some(end) if reference.as_bytes()[end] == b':' => {
+            let scheme = &reference.as_bytes()[..end];
+            scheme.first().is_some_and(u8::is_ascii_alphabetic)
+                && scheme
+                    .iter()
+                    .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.'))
+        }
+        _ => false,
     }
+}
 
-    pub fn is_client_error(&self) -> bool {
-        let status = *self as u16;
-        (400..=499).contains(&status)
+/// Split a path into the path itself and the query and fragment, including the `?` or `#` delimiter.
+fn split_query(path: &str) -> (&str, &str) {
+    path.split_at(path.find(['?', '#']).unwrap_or(path.len()))
+}
+
+fn format_port(port: u16, buf: &mut [u8; 6]) -> &str {
+    let mut start = buf.len();
+    let mut port = port;
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (port % 10) as u8;
+        port /= 10;
+        if port == 0 {
+            break;
+        }
     }
+    start -= 1;
+    buf[start] = b':';
+    core::str::from_utf8(&buf[start..]).unwrap_or_default()
+}
 
-    pub fn is_server_error(&self) -> bool {
-        let status = *self as u16;
-        (500..=599).contains(&status)
+/// Remove the `.` and `..` segments of the absolute path in `buf[start..end]` in place,
+/// returning the new end of the path.
+fn remove_dot_segments(buf: &mut [u8], start: usize, end: usize) -> usize {
+    let mut written = start;
+    let mut read = start + 1;
+    loop {
+        let segment_end = buf[read..end].iter().position(|&b| b == b'/').map_or(end, |i| read + i);
+        let last = segment_end == end;
+        match &buf[read..segment_end] {
+            b"." => {}
+            b".." => {
+                written = buf[start..written]
+                    .iter()
+                    .rposition(|&b| b == b'/')
+                    .map_or(start, |i| start + i);
+            }
+            _ => {
+                buf[written] = b'/';
+                buf.copy_within(read..segment_end, written + 1);
+                written += 1 + segment_end - read;
+                if last {
+                    break;
+                }
+                read = segment_end + 1;
+                continue;
+            }
+        }
+
+        // A path ending in a dot segment refers to a directory
+        if last {
+            buf[written] = b'/';
+            written += 1;
+            break;
+        }
+        read = segment_end + 1;
     }
+
+    written
 }
 
-impl From<u16> for Status {
-    fn from(from: u16) -> Status {
-        match from {
-            200 => Status::Ok,
-            201 => Status::Created,
-            202 => Status::Accepted,
-            204 => Status::NoContent,
-            206 => Status::PartialContent,
-            301 => Status::MovedPermanently,
-            302 => Status::Found,
-            303 => Status::SeeOther,
-            304 => Status::NotModified,
-            307 => Status::TemporaryRedirect,
-            308 => Status::PermanentRedirect,
-            400 => Status::BadRequest,
-            401 => Status::Unauthorized,
-            403 => Status::Forbidden,
-            404 => Status::NotFound,
-            405 => Status::MethodNotAllowed,
-            409 => Status::Conflict,
-            415 => Status::UnsupportedMediaType,
-            416 => Status::RangeNotSatisfiable,
-            429 => Status::TooManyRequests,
-            500 => Status::InternalServerError,
-            502 => Status::BadGateway,
-            503 => Status::ServiceUnavailable,
-            504 => Status::GatewayTimeout,
-            n => {
-                warn!("Unknown status code: {:?}", n);
-                Status::Unknown
+/// Iterator over the `name=value` parameters of a header value such as `attachment; filename="file.txt"`.
+///
+/// Values are returned as they appear in the header, including any quotes.
+struct ParameterIterator<'a>(&'a str);
+
+impl<'a> Iterator for ParameterIterator<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.0.is_empty() {
+                return None;
+            }
+
+            // Find the end of the parameter, skipping separators inside quoted strings
+            let mut quoted = false;
+            let mut escaped = false;
+            let mut end = self.0.len();
+            for (index, c) in self.0.char_indices() {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' if quoted => escaped = true,
+                    '"' => quoted = !quoted,
+                    ';' if !quoted => {
+                        end = index;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+
+            let parameter = &self.0[..end];
+            self.0 = self.0.get(end + 1..).unwrap_or_default();
+
+            if let Some((name, value)) = parameter.split_once('=') {
+                return Some((name.trim(), value.trim()));
             }
         }
     }
-}
+}
+
+/// Check whether any line of the header section is terminated by `\n` without a preceding `\r`.
+fn has_bare_lf(head: &[u8]) -> bool {
+    head.iter()
+        .enumerate()
+        .any(|(index, &byte)| byte == b'\n' && (index == 0 || head[index - 1] != b'\r'))
+}
+
+/// Split a header line without its line ending into the header name and the value without surrounding whitespace.
+fn split_header_line(line: &[u8]) -> Result<(&str, &[u8]), Error> {
+    let colon = line.iter().position(|&b| b == b':').ok_or(Error::Codec)?;
+    let name = core::str::from_utf8(&line[..colon]).map_err(|_| Error::Codec)?;
+    if name.is_empty() || name.bytes().any(|b| b.is_ascii_whitespace() || b.is_ascii_control()) {
+        return Err(Error::Codec);
+    }
+
+    let mut value = &line[colon + 1..];
+    while let [b' ' | b'\t', rest @ ..] = value {
+        value = rest;
+    }
+    while let [rest @ .., b' ' | b'\t'] = value {
+        value = rest;
+    }
+    Ok((name, value))
+}
+
+/// Get the hash in the `sha-256` entry of a `Digest` header value, such as `sha-256=<base64>, md5=<base64>`.
+#[cfg(feature = "digest")]
+fn sha256_digest(value: &[u8]) -> Option<[u8; 32]> {
+    use base64::engine::{general_purpose, Engine as _};
+
+    let value = core::str::from_utf8(value).ok()?;
+    let (_, encoded) = value
+        .split(',')
+        .filter_map(|entry| entry.trim().split_once('='))
+        .find(|(algorithm, _)| algorithm.eq_ignore_ascii_case("sha-256"))?;
+
+    let mut digest = [0; 33];
+    match general_purpose::STANDARD.decode_slice(encoded, &mut digest) {
+        Ok(32) => digest[..32].try_into().ok(),
+        _ => None,
+    }
+}
+
+/// Check whether a status code is for an interim response, which is followed by the final response.
+///
+/// `101 Switching Protocols` is final, as the connection no longer speaks HTTP/1.1 after it.
+fn is_interim(code: Option<u16>) -> bool {
+    matches!(code, Some(100..=199)) && code != Some(101)
+}
+
+/// Copy a possibly quoted string into `buf`, removing the quotes and escapes.
+fn unquote<'b>(value: &str, buf: &'b mut [u8]) -> Result<&'b [u8], Error> {
+    let Some(quoted) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+        let target = buf.get_mut(..value.len()).ok_or(Error::BufferTooSmall)?;
+        target.copy_from_slice(value.as_bytes());
+        return Ok(target);
+    };
+
+    let mut len = 0;
+    let mut escaped = false;
+    for &byte in quoted.as_bytes() {
+        if byte == b'\\' && !escaped {
+            escaped = true;
+            continue;
+        }
+        escaped = false;
+        *buf.get_mut(len).ok_or(Error::BufferTooSmall)? = byte;
+        len += 1;
+    }
+
+    Ok(&buf[..len])
+}
+
+/// Options for reading a response.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ReadOptions {
+    /// Reject ambiguous responses with [`Error::InvalidResponse`].
+    ///
+    /// When not set, ambiguous responses are handled as described in RFC 7230,
+    /// e.g. `Content-Length` is ignored if the response also has a chunked `Transfer-Encoding`.
+    pub strict: bool,
+    /// The maximum size of the response header section in bytes.
+    ///
+    /// Reading a response with a larger header section fails with [`Error::HeadersTooLarge`].
+    /// The size of the buffer provided for reading the response is used if not set, or if it is smaller.
+    /// Header sections larger than 64 KiB are always rejected.
+    pub max_header_len: Option<usize>,
+    /// Accept a status line and header lines terminated by a bare `\n` instead of `\r\n`.
+    ///
+    /// When not set, a header section with bare `\n` line endings is rejected with [`Error::InvalidResponse`].
+    pub lenient_parsing: bool,
+}
+
+impl ReadOptions {
+    fn max_header_len(&self, buf_len: usize) -> usize {
+        self.max_header_len.map_or(buf_len, |max| max.min(buf_len))
+    }
+}
+
+pub struct HeaderIterator<'a>(usize, [httparse::Header<'a>; MAX_HEADERS]);
+
+impl<'a> Iterator for HeaderIterator<'a> {
+    type Item = (&'a str, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The header array is only partially filled, the first empty header marks the end
+        let result = self.1.get(self.0).filter(|h| !h.name.is_empty());
+
+        self.0 += 1;
+
+        result.map(|h| (h.name, h.value))
+    }
+}
+
+/// Response body
+///
+/// This type contains the original header buffer provided to `read_headers`,
+/// now renamed to `body_buf`, the number of read body bytes that are available
+/// in `body_buf`, and a reader to be used for reading the remaining body.
+pub struct ResponseBody<'resp, 'buf, C>
+where
+    C: Read,
+{
+    conn: &'resp mut C,
+    reader_hint: ReaderHint,
+    /// The number of raw bytes read from the body and available in the beginning of `body_buf`.
+    raw_body_read: usize,
+    /// The buffer initially provided to read the header.
+    pub body_buf: &'buf mut [u8],
+    /// The maximum number of body bytes that may be read.
+    max_body_len: Option<usize>,
+    /// Whether the response headers allow the connection to be reused.
+    connection_reusable: bool,
+}
+
+#[derive(Clone, Copy)]
+enum ReaderHint {
+    Empty,
+    FixedLength(usize),
+    /// A chunked body, with whether the response advertised trailers in a `Trailer` header
+    Chunked {
+        trailers: bool,
+    },
+    ToEnd, // https://www.rfc-editor.org/rfc/rfc7230#section-3.3.3 pt. 7: Until end of connection
+}
+
+impl ReaderHint {
+    fn reader<R: Read>(self, raw_body: R, max_body_len: Option<usize>) -> BodyReader<R> {
+        let inner = match self {
+            ReaderHint::Empty => BodyReaderInner::Empty,
+            ReaderHint::FixedLength(content_length) => BodyReaderInner::FixedLength(FixedLengthBodyReader {
+                raw_body,
+                remaining: content_length,
+            }),
+            ReaderHint::Chunked { trailers } => BodyReaderInner::Chunked(ChunkedBodyReader::new(raw_body, trailers)),
+            ReaderHint::ToEnd => BodyReaderInner::ToEnd(raw_body),
+        };
+
+        BodyReader {
+            inner,
+            max_body_len,
+            body_read: 0,
+            connection_reusable: false,
+        }
+    }
+}
+
+impl<'resp, 'buf, C> ResponseBody<'resp, 'buf, C>
+where
+    C: Read,
+{
+    pub fn reader(self) -> BodyReader<BufferingReader<'resp, 'buf, C>> {
+        let raw_body = BufferingReader::new(self.body_buf, self.raw_body_read, self.conn);
+
+        let mut reader = self.reader_hint.reader(raw_body, self.max_body_len);
+        reader.connection_reusable = self.connection_reusable;
+        reader
+    }
+}
+
+impl<'resp, 'buf, C> ResponseBody<'resp, 'buf, C>
+where
+    C: Read + TryBufRead,
+{
+    /// Read the entire body into the buffer originally provided [`Response::read()`].
+    /// This requires that this original buffer is large enough to contain the entire body.
+    pub async fn read_to_end(self) -> Result<&'buf mut [u8], Error> {
+        self.read_to_end_or_partial().await.map_err(Error::from)
+    }
+
+    /// Read the entire body like [`ResponseBody::read_to_end`], but keep the part of the body that was
+    /// read if reading fails.
+    ///
+    /// This allows logging the beginning of an error response even if the connection is closed early
+    /// or the body does not fit in the buffer. The partial body is best-effort and never longer than the buffer.
+    pub async fn read_to_end_or_partial(mut self) -> Result<&'buf mut [u8], PartialBody<'buf>> {
+        match self.reader_hint {
+            ReaderHint::Empty => Ok(&mut []),
+            ReaderHint::FixedLength(content_length) => {
+                let mut reader = BodyReader {
+                    inner: BodyReaderInner::FixedLength(FixedLengthBodyReader {
+                        raw_body: self.conn,
+                        remaining: content_length - self.raw_body_read,
+                    }),
+                    max_body_len: self.max_body_len,
+                    body_read: self.raw_body_read,
+                    connection_reusable: false,
+                };
+                match reader.read_to_end(&mut self.body_buf[self.raw_body_read..]).await {
+                    Ok(read) => Ok(&mut self.body_buf[..read + self.raw_body_read]),
+                    Err(error) => Err(PartialBody {
+                        error,
+                        body: &self.body_buf[..reader.bytes_read().min(self.body_buf.len())],
+                    }),
+                }
+            }
+            ReaderHint::Chunked { trailers } => {
+                let raw_body = BufferingReader::new(self.body_buf, self.raw_body_read, self.conn);
+                ChunkedBodyReader::new(raw_body, trailers)
+                    .read_to_end(self.max_body_len)
+                    .await
+            }
+            ReaderHint::ToEnd => {
+                if self.max_body_len.is_some_and(|max| self.raw_body_read > max) {
+                    return Err(PartialBody {
+                        error: Error::BodyTooLarge,
+                        body: &self.body_buf[..self.raw_body_read],
+                    });
+                }
+
+                let mut reader = BodyReader {
+                    inner: BodyReaderInner::ToEnd(&mut self.conn),
+                    max_body_len: self.max_body_len,
+                    body_read: self.raw_body_read,
+                    connection_reusable: false,
+                };
+                match reader.read_to_end(&mut self.body_buf[self.raw_body_read..]).await {
+                    Ok(read) => Ok(&mut self.body_buf[..read + self.raw_body_read]),
+                    Err(error) => Err(PartialBody {
+                        error,
+                        body: &self.body_buf[..reader.bytes_read().min(self.body_buf.len())],
+                    }),
+                }
+            }
+        }
+    }
+
+    /// Discard the entire body
+    ///
+    /// Returns the number of discarded body bytes
+    pub async fn discard(self) -> Result<usize, Error> {
+        self.reader().discard().await
+    }
+}
+
+/// An error while reading the entire body, together with the part of the body that was read before it.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PartialBody<'buf> {
+    /// The error that stopped reading the body.
+    pub error: Error,
+    /// The decoded body bytes that were read before the error.
+    pub body: &'buf [u8],
+}
+
+impl From<PartialBody<'_>> for Error {
+    fn from(partial: PartialBody<'_>) -> Self {
+        partial.error
+    }
+}
+
+/// A handler that processes a response body, see [`Response::with_body`].
+pub trait BodyHandler {
+    /// The result of processing the body
+    type Output;
+
+    /// Process the body by reading from `reader`.
+    ///
+    /// The handler does not need to read the entire body.
+    async fn handle<B: Read>(self, reader: &mut BodyReader<B>) -> Result<Self::Output, Error>;
+}
+
+/// A body reader
+///
+/// The body framing (empty, fixed length, chunked or read until the connection closes)
+/// is private; use [`BodyReader::content_length`] to find the length of a framed body.
+pub struct BodyReader<B> {
+    inner: BodyReaderInner<B>,
+    /// The maximum number of body bytes that may be read.
+    max_body_len: Option<usize>,
+    /// The number of body bytes read so far.
+    body_read: usize,
+    /// Whether the response headers allow the connection to be reused.
+    connection_reusable: bool,
+}
+
+enum BodyReaderInner<B> {
+    Empty,
+    FixedLength(FixedLengthBodyReader<B>),
+    Chunked(ChunkedBodyReader<B>),
+    ToEnd(B),
+}
+
+impl<B> BodyReader<B>
+where
+    B: Read,
+{
+    fn is_done(&self) -> bool {
+        match &self.inner {
+            BodyReaderInner::Empty => true,
+            BodyReaderInner::FixedLength(reader) => reader.remaining == 0,
+            BodyReaderInner::Chunked(reader) => reader.is_done(),
+            BodyReaderInner::ToEnd(_) => false,
+        }
+    }
+
+    /// Get the number of body bytes read so far.
+    pub fn bytes_read(&self) -> usize {
+        self.body_read
+    }
+
+    /// Get the total length of the body, if it is known.
+    ///
+    /// This is `None` for chunked bodies and bodies delimited by the end of the connection.
+    /// Together with [`BodyReader::bytes_read`] it can be used to report the download progress.
+    pub fn content_length(&self) -> Option<usize> {
+        match &self.inner {
+            BodyReaderInner::Empty => Some(0),
+            BodyReaderInner::FixedLength(reader) => Some(self.body_read + reader.remaining),
+            BodyReaderInner::Chunked(_) | BodyReaderInner::ToEnd(_) => None,
+        }
+    }
+
+    /// Get the trailer headers sent after the last chunk of a chunked body.
+    ///
+    /// Returns `None` until the body is completely read, or if there are no trailers.
+    /// Trailers are, for example, used by servers to send a digest computed over the streamed body.
+    /// They are only kept if the response advertised them in a `Trailer` header, up to 128 bytes in total.
+    /// Trailers that do not fit are skipped.
+    pub fn trailers(&self) -> Option<HeaderIterator<'_>> {
+        match &self.inner {
+            BodyReaderInner::Chunked(reader) => reader.trailers(),
+            _ => None,
+        }
+    }
+
+    /// Get the number of body bytes that may still be read before exceeding the limit, if any
+    fn remaining_allowed(&self) -> Result<Option<usize>, Error> {
+        let Some(max_body_len) = self.max_body_len else {
+            return Ok(None);
+        };
+
+        // A fixed length body is known to be too large before it is read
+        if let BodyReaderInner::FixedLength(reader) = &self.inner {
+            if self.body_read + reader.remaining > max_body_len {
+                return Err(Error::BodyTooLarge);
+            }
+        }
+
+        Ok(Some(max_body_len - self.body_read))
+    }
+
+    /// Read the entire body
+    pub async fn read_to_end(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut len = 0;
+        while len < buf.len() {
+            match self.read(&mut buf[len..]).await {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(e) => return Err(e),
+            }
+        }
+
+        if !self.is_done() {
+            let more = match &mut self.inner {
+                BodyReaderInner::FixedLength(reader) => {
+                    warn!("FixedLength: {} bytes remained", reader.remaining);
+                    true
+                }
+                BodyReaderInner::ToEnd(reader) if len == buf.len() => {
+                    warn!("ToEnd: Buffer full, waiting to see if there is unread data.");
+
+                    let mut b = [0];
+                    matches!(reader.read(&mut b).await, Ok(1))
+                }
+
+                BodyReaderInner::ToEnd(_) => false,
+                BodyReaderInner::Chunked(reader) if len == buf.len() => {
+                    // The final chunk may not have been read yet
+                    let mut b = [0];
+                    reader.read(&mut b).await? != 0
+                }
+                _ => true,
+            };
+
+            if more {
+                return Err(Error::BufferTooSmall);
+            }
+        }
+
+        Ok(len)
+    }
+
+    /// Read exactly enough bytes to fill `buf`.
+    ///
+    /// Returns [`Error::UnexpectedEof`] if the body ends before `buf` is filled.
+    pub async fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<(), Error> {
+        while !buf.is_empty() {
+            match self.read(buf).await? {
+                0 => return Err(Error::UnexpectedEof),
+                n => buf = &mut buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    async fn discard(&mut self) -> Result<usize, Error> {
+        let mut body_len = 0;
+        let mut buf = [0; 128];
+        loop {
+            let buf = self.read(&mut buf).await?;
+            if buf == 0 {
+                break;
+            }
+            body_len += buf;
+        }
+
+        Ok(body_len)
+    }
+}
+
+impl<C> BodyReader<BufferingReader<'_, '_, C>>
+where
+    C: Read,
+{
+    /// Check whether the connection can be reused for another request.
+    ///
+    /// This is the case if the response headers allow it, see [`Response::is_connection_reusable`],
+    /// the body is completely read, and no bytes following the body were read from the connection.
+    pub fn is_connection_reusable(&self) -> bool {
+        self.connection_reusable && self.is_done() && !self.has_trailing_bytes()
+    }
+
+    /// Whether bytes following the end of the body were buffered while reading the body
+    fn has_trailing_bytes(&self) -> bool {
+        let raw_body = match &self.inner {
+            BodyReaderInner::Empty => return false,
+            BodyReaderInner::FixedLength(reader) => &reader.raw_body,
+            BodyReaderInner::Chunked(reader) => &reader.raw_body,
+            BodyReaderInner::ToEnd(raw_body) => raw_body,
+        };
+        !raw_body.buffer.is_empty()
+    }
+}
+
+impl<B> ErrorType for BodyReader<B> {
+    type Error = Error;
+}
+
+impl<B> Read for BodyReader<B>
+where
+    B: Read,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let allowed = self.remaining_allowed()?;
+
+        // Once the limit is reached, read a single byte to detect if the body is too large
+        let buf = match allowed {
+            Some(allowed) => {
+                let len = buf.len().min(allowed.max(1));
+                &mut buf[..len]
+            }
+            None => buf,
+        };
+
+        let len = match &mut self.inner {
+            BodyReaderInner::Empty => Ok(0),
+            BodyReaderInner::FixedLength(reader) => reader.read(buf).await,
+            BodyReaderInner::Chunked(reader) => reader.read(buf).await,
+            BodyReaderInner::ToEnd(conn) => conn.read(buf).await.map_err(|e| Error::from(e.kind())),
+        }?;
+
+        if allowed.is_some_and(|allowed| len > allowed) {
+            return Err(Error::BodyTooLarge);
+        }
+
+        self.body_read += len;
+        Ok(len)
+    }
+}
+
+impl<B> BufRead for BodyReader<B>
+where
+    B: BufRead + Read,
+{
+    async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        let allowed = self.remaining_allowed()?;
+
+        let buf = match &mut self.inner {
+            BodyReaderInner::Empty => Ok(&[][..]),
+            BodyReaderInner::FixedLength(reader) => reader.fill_buf().await,
+            BodyReaderInner::Chunked(reader) => reader.fill_buf().await,
+            BodyReaderInner::ToEnd(conn) => conn.fill_buf().await.map_err(|e| Error::from(e.kind())),
+        }?;
+
+        match allowed {
+            Some(0) if !buf.is_empty() => Err(Error::BodyTooLarge),
+            Some(allowed) => Ok(&buf[..buf.len().min(allowed)]),
+            None => Ok(buf),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match &mut self.inner {
+            BodyReaderInner::Empty => {}
+            BodyReaderInner::FixedLength(reader) => reader.consume(amt),
+            BodyReaderInner::Chunked(reader) => reader.consume(amt),
+            BodyReaderInner::ToEnd(conn) => conn.consume(amt),
+        }
+
+        self.body_read += amt;
+    }
+}
+
+/// HTTP status types
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Status {
+    Ok = 200,
+    Created = 201,
+    Accepted = 202,
+    NoContent = 204,
+    PartialContent = 206,
+    MovedPermanently = 301,
+    Found = 302,
+    SeeOther = 303,
+    NotModified = 304,
+    TemporaryRedirect = 307,
+    PermanentRedirect = 308,
+    BadRequest = 400,
+    Unauthorized = 401,
+    Forbidden = 403,
+    NotFound = 404,
+    MethodNotAllowed = 405,
+    Conflict = 409,
+    PreconditionFailed = 412,
+    UnsupportedMediaType = 415,
+    RangeNotSatisfiable = 416,
+    TooManyRequests = 429,
+    InternalServerError = 500,
+    BadGateway = 502,
+    ServiceUnavailable = 503,
+    GatewayTimeout = 504,
+    Unknown = 0,
+}
+
+impl Status {
+    pub fn is_informational(&self) -> bool {
+        let status = *self as u16;
+        (100..=199).contains(&status)
+    }
+
+    pub fn is_successful(&self) -> bool {
+        let status = *self as u16;
+        (200..=299).contains(&status)
+    }
+
+    pub fn is_redirection(&self) -> bool {
+        let status = *self as u16;
+        (300..=399).contains(&status)
+    }
+
+    pub fn is_client_error(&self) -> bool {
+        let status = *self as u16;
+        (400..=499).contains(&status)
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        let status = *self as u16;
+        (500..=599).contains(&status)
+    }
+}
+
+impl From<u16> for Status {
+    fn from(from: u16) -> Status {
+        match from {
+            200 => Status::Ok,
+            201 => Status::Created,
+            202 => Status::Accepted,
+            204 => Status::NoContent,
+            206 => Status::PartialContent,
+            301 => Status::MovedPermanently,
+            302 => Status::Found,
+            303 => Status::SeeOther,
+            304 => Status::NotModified,
+            307 => Status::TemporaryRedirect,
+            308 => Status::PermanentRedirect,
+            400 => Status::BadRequest,
+            401 => Status::Unauthorized,
+            403 => Status::Forbidden,
+            404 => Status::NotFound,
+            405 => Status::MethodNotAllowed,
+            409 => Status::Conflict,
+            412 => Status::PreconditionFailed,
+            415 => Status::UnsupportedMediaType,
+            416 => Status::RangeNotSatisfiable,
+            429 => Status::TooManyRequests,
+            500 => Status::InternalServerError,
+            502 => Status::BadGateway,
+            503 => Status::ServiceUnavailable,
+            504 => Status::GatewayTimeout,
+            n => {
+                warn!("Unknown status code: {:?}", n);
+                Status::Unknown
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::Infallible;
+
+    use embedded_io::ErrorType;
+    use embedded_io_async::{BufRead, Read};
+    use nourl::Url;
+
+    use crate::{
+        headers::{ContentType, RetryAfter},
+        reader::BufferingReader,
+        request::{HttpVersion, Method},
+        response::{
+            chunked::ChunkedBodyReader, malformed_header_line, resolve_reference, BodyHandler, BodyReader, ReadOptions,
+            Response, Status,
+        },
+        Error, TryBufRead,
+    };
+
+    #[tokio::test]
+    async fn can_read_with_identical_duplicate_content_length() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\nContent-Length: 11\r\n\r\nHELLO WORLD",
+        );
+        let mut header_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+        assert_eq!(Some(11), response.content_length);
+        let body = response.body().read_to_end().await.unwrap();
+        assert_eq!(b"HELLO WORLD", body);
+    }
+
+    #[tokio::test]
+    async fn conflicting_content_length_is_rejected() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\nContent-Length: 5\r\n\r\nHELLO WORLD",
+        );
+        let mut header_buf = [0; 200];
+        let result = Response::read(&mut conn, Method::GET, &mut header_buf).await;
+
+        assert!(matches!(result, Err(Error::InvalidResponse)));
+    }
+
+    #[tokio::test]
+    async fn can_read_with_content_length_with_same_buffer() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD");
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        let body = response.body().read_to_end().await.unwrap();
+
+        assert_eq!(b"HELLO WORLD", body);
+        assert!(conn.is_exhausted());
+    }
+
+    #[tokio::test]
+    async fn bare_lf_is_rejected_by_default() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\n\r\nHELLO WORLD");
+        let mut response_buf = [0; 200];
+        let result = Response::read(&mut conn, Method::GET, &mut response_buf).await;
+
+        assert!(matches!(result, Err(Error::InvalidResponse)));
+    }
+
+    #[tokio::test]
+    async fn can_read_bare_lf_with_lenient_parsing() {
+        let options = ReadOptions {
+            lenient_parsing: true,
+            ..Default::default()
+        };
+        for response in [
+            b"HTTP/1.1 200 OK\nContent-Length: 11\nX-Custom: yes\n\nHELLO WORLD".as_slice(),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 11\nX-Custom: yes\r\n\nHELLO WORLD".as_slice(),
+        ] {
+            let mut conn = FakeSingleReadConnection::new(response);
+            let mut response_buf = [0; 200];
+            let response = Response::read_with_options(&mut conn, Method::GET, &mut response_buf, options)
+                .await
+                .unwrap();
+
+            assert_eq!(Some(b"yes".as_slice()), response.header("x-custom"));
+            assert_eq!(b"HELLO WORLD", response.body().read_to_end().await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn chunked_encoding_overrides_content_length() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        assert_eq!(None, response.content_length);
+
+        let body = response.body().read_to_end().await.unwrap();
+
+        assert_eq!(b"HELLO", body);
+        assert!(conn.is_exhausted());
+    }
+
+    #[tokio::test]
+    async fn content_length_and_chunked_encoding_is_rejected_when_strict() {
+        // Lenient parsing of line endings does not relax the handling of ambiguous responses
+        for lenient_parsing in [false, true] {
+            let mut conn = FakeSingleReadConnection::new(
+                b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n0\r\n\r\n",
+            );
+            let mut response_buf = [0; 200];
+            let options = ReadOptions {
+                strict: true,
+                lenient_parsing,
+                ..Default::default()
+            };
+            let result = Response::read_with_options(&mut conn, Method::GET, &mut response_buf, options).await;
+
+            assert!(matches!(result, Err(Error::InvalidResponse)));
+        }
+    }
+
+    #[tokio::test]
+    async fn connection_is_reusable_after_body_is_read() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD");
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+        assert_eq!(HttpVersion::Http11, response.version());
+        assert!(!response.is_connection_reusable());
+
+        let mut reader = response.body().reader();
+        let mut body_buf = [0; 5];
+        reader.read_exact(&mut body_buf).await.unwrap();
+        assert!(!reader.is_connection_reusable());
+
+        assert_eq!(6, reader.discard().await.unwrap());
+        assert!(reader.is_connection_reusable());
+    }
+
+    #[tokio::test]
+    async fn abort_drains_short_body() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n6\r\n WORLD\r\n0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        assert!(response.abort(11).await.unwrap());
+        assert!(conn.is_exhausted());
+    }
+
+    #[tokio::test]
+    async fn abort_does_not_drain_long_body() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD");
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+        assert!(!response.abort(10).await.unwrap());
+
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n6\r\n WORLD\r\n0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+        assert!(!response.abort(10).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn body_reader_reports_progress() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD");
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        let mut reader = response.body().reader();
+        assert_eq!((0, Some(11)), (reader.bytes_read(), reader.content_length()));
+        let mut body_buf = [0; 5];
+        reader.read_exact(&mut body_buf).await.unwrap();
+        assert_eq!((5, Some(11)), (reader.bytes_read(), reader.content_length()));
+
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        let mut reader = response.body().reader();
+        reader.discard().await.unwrap();
+        assert_eq!((5, None), (reader.bytes_read(), reader.content_length()));
+    }
+
+    #[tokio::test]
+    async fn connection_is_not_reusable_when_closed() {
+        let mut conn =
+            FakeSingleReadConnection::new(b"HTTP/1.1 204 No Content\r\nConnection: close\r\nContent-Length: 0\r\n\r\n");
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        assert!(!response.is_connection_reusable());
+    }
+
+    #[tokio::test]
+    async fn http10_connection_is_reusable_with_keep_alive() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.0 204 No Content\r\nContent-Length: 0\r\n\r\n");
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+        assert_eq!(HttpVersion::Http10, response.version());
+        assert!(!response.is_connection_reusable());
+
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.0 204 No Content\r\nConnection: Keep-Alive\r\nContent-Length: 0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+        assert!(response.is_connection_reusable());
+    }
+
+    #[tokio::test]
+    async fn can_get_keep_alive_params() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 204 No Content\r\nKeep-Alive: timeout=5, max=100\r\nContent-Length: 0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        let keep_alive = response.keep_alive_params().unwrap();
+        assert_eq!(Some(5), keep_alive.timeout());
+        assert_eq!(Some(100), keep_alive.max());
+    }
+
+    #[tokio::test]
+    async fn keep_alive_params_is_none_without_header() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n");
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        assert!(response.keep_alive_params().is_none());
+    }
+
+    #[tokio::test]
+    async fn can_get_content_disposition_filename() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nContent-Disposition: attachment; filename=\"a \\\"b\\\"; c.txt\"\r\nContent-Length: 0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        let mut buf = [0; 32];
+        assert_eq!(
+            Some("a \"b\"; c.txt"),
+            response.content_disposition_filename(&mut buf).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn extended_content_disposition_filename_is_preferred() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nContent-Disposition: attachment; filename=\"file.txt\"; filename*=UTF-8''my%20file.txt\r\nContent-Length: 0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        let mut buf = [0; 32];
+        assert_eq!(
+            Some("my file.txt"),
+            response.content_disposition_filename(&mut buf).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn can_get_allowed_methods() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 204 No Content\r\nAllow: OPTIONS, GET,HEAD, PROPFIND\r\nContent-Length: 0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::OPTIONS, &mut response_buf)
+            .await
+            .unwrap();
+
+        let mut methods = response.allowed_methods();
+        assert_eq!(Some(Method::OPTIONS), methods.next());
+        assert_eq!(Some(Method::GET), methods.next());
+        assert_eq!(Some(Method::HEAD), methods.next());
+        assert_eq!(None, methods.next());
+    }
+
+    #[tokio::test]
+    async fn headers_larger_than_buffer_are_rejected() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD");
+        let mut response_buf = [0; 20];
+        let result = Response::read(&mut conn, Method::GET, &mut response_buf).await;
+
+        assert!(matches!(result, Err(Error::HeadersTooLarge { needed: 21 })));
+    }
+
+    #[tokio::test]
+    async fn headers_larger_than_max_header_len_are_rejected() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD");
+        conn.read_length = 100;
+        let mut response_buf = [0; 200];
+        let options = ReadOptions {
+            max_header_len: Some(20),
+            ..Default::default()
+        };
+        let result = Response::read_with_options(&mut conn, Method::GET, &mut response_buf, options).await;
+
+        assert!(matches!(result, Err(Error::HeadersTooLarge { needed: 39 })));
+    }
+
+    #[tokio::test]
+    async fn read_exact_from_chunked_body() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nHEL\r\n8\r\nLO WORLD\r\n0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+        let mut reader = response.body().reader();
+
+        let mut buf = [0; 5];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(b"HELLO", &buf);
+
+        let mut buf = [0; 7];
+        assert!(matches!(reader.read_exact(&mut buf).await, Err(Error::UnexpectedEof)));
+    }
+
+    #[tokio::test]
+    async fn read_exact_from_fixed_length_body() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD");
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+        let mut reader = response.body().reader();
+
+        let mut buf = [0; 11];
+        reader.read_exact(&mut buf).await.unwrap();
+        assert_eq!(b"HELLO WORLD", &buf);
+
+        let mut buf = [0; 1];
+        assert!(matches!(reader.read_exact(&mut buf).await, Err(Error::UnexpectedEof)));
+    }
+
+    #[tokio::test]
+    async fn informational_responses_are_skipped() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD",
+        );
+        conn.read_length = 10;
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        assert_eq!(Status::Ok, response.status);
+        assert!(response.headers().all(|(name, _)| name != "Link"));
+        let body = response.body().read_to_end().await.unwrap();
+        assert_eq!(b"HELLO WORLD", body);
+    }
+
+    #[tokio::test]
+    async fn can_visit_headers_with_small_buffer() {
+        for read_length in [1, 10, 100] {
+            let mut conn = FakeSingleReadConnection::new(
+                b"HTTP/1.1 100 Continue\r\nX-Interim: yes\r\n\r\nHTTP/1.1 200 OK\r\nSet-Cookie: session=0123456789abcdef\r\nContent-Length: 11\r\nX-Request-Id:  42 \r\nCache-Control: no-cache, no-store, must-revalidate\r\nConnection: close\r\n\r\nHELLO WORLD",
+            );
+            conn.read_length = read_length;
+            let mut buf = [0; 112];
+            let mut visited = std::vec::Vec::new();
+            let response = Response::read_with_header_visitor(&mut conn, Method::GET, &mut buf, |name, value| {
+                visited.push((name.to_owned(), value.to_vec()))
+            })
+            .await
+            .unwrap();
+
+            assert_eq!(Status::Ok, response.status);
+            assert_eq!(Some(11), response.content_length);
+            assert_eq!(Some(b"close".as_slice()), response.header("connection"));
+            assert_eq!(None, response.header("x-request-id"));
+            assert!(!response.is_connection_reusable());
+            let body = response.body().read_to_end().await.unwrap();
+            assert_eq!(b"HELLO WORLD", body);
+
+            let names: std::vec::Vec<_> = visited.iter().map(|(name, _)| name.as_str()).collect();
+            assert_eq!(
+                [
+                    "Set-Cookie",
+                    "Content-Length",
+                    "X-Request-Id",
+                    "Cache-Control",
+                    "Connection"
+                ],
+                names.as_slice()
+            );
+            assert_eq!(b"42", visited[2].1.as_slice());
+        }
+    }
+
+    #[tokio::test]
+    async fn header_visitor_fails_if_line_does_not_fit() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nCache-Control: no-cache, no-store, must-revalidate\r\n\r\n",
+        );
+        conn.read_length = 100;
+        let mut buf = [0; 32];
+        let result = Response::read_with_header_visitor(&mut conn, Method::GET, &mut buf, |_, _| {}).await;
+
+        assert!(matches!(result, Err(Error::HeadersTooLarge { .. })));
+    }
+
+    #[tokio::test]
+    async fn can_get_buffered_body() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD");
+        conn.read_length = 100;
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        assert_eq!(b"HELLO WORLD", response.body_buffered());
+        assert_eq!(Some(11), response.content_length);
+    }
+
+    #[tokio::test]
+    async fn buffered_body_is_empty_if_only_headers_are_read() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD");
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        assert_eq!(b"", response.body_buffered());
+        let body = response.body().read_to_end().await.unwrap();
+        assert_eq!(b"HELLO WORLD", body);
+    }
+
+    #[tokio::test]
+    async fn can_get_retry_after() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 429 Too Many Requests\r\nRetry-After: 30\r\nContent-Length: 0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        assert_eq!(Status::TooManyRequests, response.status);
+        assert_eq!(Some(RetryAfter::Delay(30)), response.retry_after());
+    }
+
+    #[tokio::test]
+    async fn can_get_relative_location() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 302 Found\r\nLocation: ../other?page=2\r\nContent-Length: 0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        let base = Url::parse("http://example.com:8080/a/b/c?q=1").unwrap();
+        let mut buf = [0; 64];
+        let location = response.location(&base, &mut buf).unwrap().unwrap();
+        assert_eq!("example.com", location.host());
+        assert_eq!(Some(8080), location.port());
+        assert_eq!("/a/other?page=2", location.path());
+    }
+
+    #[test]
+    fn resolve_location_references() {
+        let base = Url::parse("https://example.com/a/b/c").unwrap();
+        let mut buf = [0; 64];
+        let mut resolve = |reference| resolve_reference(&base, reference, &mut buf).unwrap().to_owned();
+
+        assert_eq!("http://other.com/x", resolve("http://other.com/x"));
+        assert_eq!("https://other.com/x", resolve("//other.com/x"));
+        assert_eq!("https://example.com/x/y", resolve("/x/./y"));
+        assert_eq!("https://example.com/a/b/d", resolve("d"));
+        assert_eq!("https://example.com/a/d", resolve("../d"));
+        assert_eq!("https://example.com/", resolve("../../../../d/.."));
+        assert_eq!("https://example.com/a/b/", resolve("."));
+        assert_eq!("https://example.com/a/b/c?x", resolve("?x"));
+        assert_eq!("https://example.com/a/b/c", resolve(""));
+    }
+
+    #[test]
+    fn resolve_location_references_containing_urls() {
+        let base = Url::parse("https://example.com/a/b/c").unwrap();
+        let mut buf = [0; 64];
+        let mut resolve = |reference| resolve_reference(&base, reference, &mut buf).unwrap().to_owned();
+
+        assert_eq!(
+            "https://example.com/login?next=https://x/",
+            resolve("/login?next=https://x/")
+        );
+        assert_eq!("https://example.com/a/b/d?next=http://x/", resolve("d?next=http://x/"));
+        assert_eq!("https://example.com/a/b/c#http://x/", resolve("#http://x/"));
+        assert_eq!("HTTP://other.com/x", resolve("HTTP://other.com/x"));
+    }
+
+    #[test]
+    fn resolve_location_references_with_ipv6_base() {
+        let mut buf = [0; 64];
+        let base = Url::parse("http://[::1]:8080/a/b").unwrap();
+        assert_eq!("http://[::1]:8080/x", resolve_reference(&base, "/x", &mut buf).unwrap());
+
+        let base = Url::parse("http://[fe80::1%3]/a/b").unwrap();
+        assert_eq!(
+            "http://[fe80::1%3]/a/x",
+            resolve_reference(&base, "x", &mut buf).unwrap()
+        );
+        let location = Url::parse(resolve_reference(&base, "x", &mut buf).unwrap()).unwrap();
+        assert_eq!("fe80::1", location.host());
+        assert_eq!(Some(3), location.scope_id());
+    }
+
+    #[tokio::test]
+    async fn can_get_auth_challenges() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic realm=\"device\"\r\nWWW-Authenticate: Bearer\r\nContent-Length: 0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        let challenge = response.auth_challenge().unwrap();
+        assert_eq!("Basic", challenge.scheme());
+        assert_eq!(Some("device"), challenge.param("realm"));
+        assert_eq!(2, response.auth_challenges().count());
+    }
+
+    #[tokio::test]
+    async fn can_get_etag() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 412 Precondition Failed\r\nETag: \"v2\"\r\nContent-Length: 0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::PUT, &mut response_buf).await.unwrap();
+
+        assert_eq!(Status::PreconditionFailed, response.status);
+        assert_eq!(Some("\"v2\""), response.etag());
+    }
+
+    #[tokio::test]
+    async fn reading_headers_does_not_read_body() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 404 Not Found\r\nContent-Length: 5\r\n\r\nHELLO");
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        assert_eq!(Status::NotFound, response.status());
+        assert!(response.body_buffered().is_empty());
+        assert_eq!(5, response.body().discard().await.unwrap());
+        assert!(conn.is_exhausted());
+    }
 
-#[cfg(test)]
-mod tests {
-    use core::convert::Infallible;
+    #[tokio::test]
+    async fn can_look_up_headers() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nX-Empty:\r\nContent-Length: 0\r\nX-Custom: first\r\nx-custom: second\r\n\r\n",
+        );
+        conn.read_length = 100;
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
 
-    use embedded_io::ErrorType;
-    use embedded_io_async::Read;
+        assert_eq!(Some(b"first".as_slice()), response.header("x-custom"));
+        assert_eq!(Some(b"0".as_slice()), response.header("Content-Length"));
+        assert_eq!(Some(b"".as_slice()), response.header("x-empty"));
+        assert_eq!(None, response.header("x-missing"));
+
+        let mut headers = response.headers();
+        assert_eq!(Some(("X-Empty", b"".as_slice())), headers.next());
+        assert_eq!(Some(("Content-Length", b"0".as_slice())), headers.next());
+        assert_eq!(Some(("X-Custom", b"first".as_slice())), headers.next());
+        assert_eq!(Some(("x-custom", b"second".as_slice())), headers.next());
+        assert_eq!(None, headers.next());
+    }
 
-    use crate::{
-        reader::BufferingReader,
-        request::Method,
-        response::{chunked::ChunkedBodyReader, Response},
-        Error, TryBufRead,
-    };
+    #[tokio::test]
+    async fn can_get_content_type_and_charset() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=\"utf-8\"\r\nContent-Length: 0\r\n\r\n",
+        );
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        assert_eq!(Some(ContentType::TextPlain), response.content_type());
+        assert_eq!(Some("utf-8"), response.charset());
+        // The field only knows content types without parameters
+        assert_eq!(Some(ContentType::ApplicationOctetStream), response.content_type);
+    }
 
     #[tokio::test]
-    async fn can_read_with_content_length_with_same_buffer() {
-        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD");
+    async fn content_type_field_is_still_set() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 0\r\n\r\n",
+        );
         let mut response_buf = [0; 200];
         let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
 
-        let body = response.body().read_to_end().await.unwrap();
+        assert_eq!(Some(ContentType::ApplicationJson), response.content_type);
+        assert_eq!(Some(ContentType::ApplicationJson), response.content_type());
+    }
 
-        assert_eq!(b"HELLO WORLD", body);
-        assert!(conn.is_exhausted());
+    #[tokio::test]
+    async fn content_type_is_none_without_header() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        assert_eq!(None, response.content_type());
+        assert_eq!(None, response.charset());
     }
 
     #[tokio::test]
@@ -531,6 +2291,47 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn read_to_end_or_partial_keeps_truncated_body() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 500 Internal Server Error\r\nContent-Length: 100\r\n\r\n{\"error\": \"out of",
+        );
+        conn.read_length = 16;
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        let partial = response.body().read_to_end_or_partial().await.unwrap_err();
+        assert!(matches!(partial.error, Error::BufferTooSmall));
+        assert_eq!(b"{\"error\": \"out of", partial.body);
+    }
+
+    #[tokio::test]
+    async fn read_to_end_or_partial_keeps_truncated_chunked_body() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 500 Internal Server Error\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nHELLO \r\n5\r\nWOR",
+        );
+        conn.read_length = 8;
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        let partial = response.body().read_to_end_or_partial().await.unwrap_err();
+        assert!(matches!(partial.error, Error::ConnectionAborted));
+        assert_eq!(b"HELLO WOR", partial.body);
+    }
+
+    #[tokio::test]
+    async fn read_to_end_or_partial_is_bounded_by_buffer() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 500 Internal Server Error\r\n\r\nThe quick brown fox jumps over the lazy dog, twice.",
+        );
+        let mut response_buf = [0; 45];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        let partial = response.body().read_to_end_or_partial().await.unwrap_err();
+        assert!(matches!(partial.error, Error::BufferTooSmall));
+        assert_eq!(b"The quick brown fox jumps over the lazy dog, ", partial.body);
+    }
+
     #[tokio::test]
     async fn can_discard_with_content_length() {
         let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD");
@@ -541,6 +2342,85 @@ mod tests {
         assert!(conn.is_exhausted());
     }
 
+    #[tokio::test]
+    async fn successful_connect_response_is_tunnel() {
+        let mut conn =
+            FakeSingleReadConnection::new(b"HTTP/1.1 200 Connection Established\r\n\r\nSSH-2.0-OpenSSH_9.6\r\n");
+        conn.read_length = 50;
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::CONNECT, &mut response_buf)
+            .await
+            .unwrap();
+        assert!(!response.is_connection_reusable());
+
+        let (tunnel, received) = response.into_tunnel().unwrap();
+        assert_eq!(b"SSH-2.0-Ope", received);
+        let mut buf = [0; 50];
+        let len = tunnel.read(&mut buf).await.unwrap();
+        assert_eq!(b"nSSH_9.6\r\n", &buf[..len]);
+
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n");
+        let response = Response::read(&mut conn, Method::CONNECT, &mut response_buf)
+            .await
+            .unwrap();
+        assert!(response.into_tunnel().is_none());
+
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+        assert!(response.into_tunnel().is_none());
+    }
+
+    /// Sums the given number of bytes from the start of the body
+    struct ChecksumHandler(usize);
+
+    impl BodyHandler for ChecksumHandler {
+        type Output = u32;
+
+        async fn handle<B: Read>(self, reader: &mut BodyReader<B>) -> Result<u32, Error> {
+            let mut buf = [0; 4];
+            let mut remaining = self.0;
+            let mut sum = 0;
+            while remaining > 0 {
+                let len = reader.read(&mut buf[..remaining.min(4)]).await?;
+                sum += buf[..len].iter().map(|&b| b as u32).sum::<u32>();
+                remaining -= len;
+            }
+            Ok(sum)
+        }
+    }
+
+    #[tokio::test]
+    async fn with_body_discards_unread_body() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nHELLO \r\n5\r\nWORLD\r\n0\r\n\r\nHTTP/1.1",
+        );
+        let mut response_buf = [0; 100];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        let sum = response.with_body(ChecksumHandler(2)).await.unwrap();
+        assert_eq!((b'H' + b'E') as u32, sum);
+        assert_eq!(b"HTTP/1.1", &conn.response[conn.offset..]);
+    }
+
+    #[tokio::test]
+    async fn can_consume_body() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nHELLO \r\n5\r\nWORLD\r\n0\r\n\r\nHTTP/1.1",
+        );
+        let mut response_buf = [0; 100];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        assert_eq!(11, response.consume_body().await.unwrap());
+        assert_eq!(b"HTTP/1.1", &conn.response[conn.offset..]);
+
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD");
+        let mut response_buf = [0; 100];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+        let result = response.max_body_len(10).consume_body().await;
+        assert!(matches!(result, Err(Error::BodyTooLarge)));
+    }
+
     #[tokio::test]
     async fn incorrect_fragment_length_does_not_panic() {
         let mut conn = FakeSingleReadConnection::new(
@@ -555,6 +2435,33 @@ mod tests {
         assert!(matches!(error, Error::Codec));
     }
 
+    #[tokio::test]
+    async fn malformed_status_line_is_reported() {
+        let mut conn = FakeSingleReadConnection::new(b"SSH-2.0-OpenSSH_9.6\r\n");
+        let mut header_buf = [0; 200];
+
+        let result = Response::read(&mut conn, Method::GET, &mut header_buf).await;
+
+        assert!(matches!(result, Err(Error::MalformedStatusLine)));
+    }
+
+    #[tokio::test]
+    async fn malformed_header_line_is_reported() {
+        let head = b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nBad Header\r\n\r\n";
+        let mut conn = FakeSingleReadConnection::new(head);
+        let mut header_buf = [0; 200];
+
+        let result = Response::read(&mut conn, Method::GET, &mut header_buf).await;
+
+        assert!(matches!(result, Err(Error::Codec)));
+        assert_eq!(Some((36, b"Bad Header".as_slice())), malformed_header_line(head));
+        assert_eq!(
+            Some((17, b"X-Value: a\x01b".as_slice())),
+            malformed_header_line(b"HTTP/1.1 200 OK\r\nX-Value: a\x01b\r\n\r\n")
+        );
+        assert_eq!(None, malformed_header_line(b"HTTP/1.1 200 OK\r\nX-Value: a\tb\r\n\r\n"));
+    }
+
     #[tokio::test]
     async fn can_read_with_chunked_encoding() {
         let mut conn = FakeSingleReadConnection::new(
@@ -653,6 +2560,84 @@ mod tests {
         assert!(conn.is_exhausted());
     }
 
+    #[tokio::test]
+    async fn connection_is_not_reusable_with_bytes_after_body() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHELLO WORLD");
+        conn.read_length = 100;
+        let mut response_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+        assert_eq!(b"HELLO", response.body_buffered());
+
+        let mut reader = response.body().reader();
+        let mut body_buf = [0; 200];
+        assert_eq!(5, reader.read_to_end(&mut body_buf).await.unwrap());
+        assert!(!reader.is_connection_reusable());
+
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHELLO WORLD");
+        conn.read_length = 100;
+        let options = ReadOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let result = Response::read_with_options(&mut conn, Method::GET, &mut response_buf, options).await;
+        assert!(matches!(result, Err(Error::InvalidResponse)));
+    }
+
+    #[tokio::test]
+    async fn connection_is_not_reusable_with_bytes_buffered_after_chunked_body() {
+        for (response, reusable) in [
+            (
+                b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n0\r\n\r\n".as_slice(),
+                true,
+            ),
+            (
+                b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n0\r\n\r\nEXTRA".as_slice(),
+                false,
+            ),
+        ] {
+            let mut conn = FakeSingleReadConnection::new(response);
+            // Read the header section in one read, and the rest of the body into the buffer with the next
+            conn.read_length = 45;
+            let mut response_buf = [0; 200];
+            let response = Response::read(&mut conn, Method::GET, &mut response_buf).await.unwrap();
+
+            let mut reader = response.body().reader();
+            loop {
+                let len = reader.fill_buf().await.unwrap().len();
+                if len == 0 {
+                    break;
+                }
+                reader.consume(len);
+            }
+            assert_eq!(reusable, reader.is_connection_reusable());
+        }
+    }
+
+    #[tokio::test]
+    async fn can_read_http10_body_delimited_by_connection_close() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\n\r\nHELLO WORLD");
+        let mut header_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+        let mut reader = response.body().reader();
+        let mut body_buf = [0; 200];
+        let len = reader.read_to_end(&mut body_buf).await.unwrap();
+
+        assert_eq!(b"HELLO WORLD", &body_buf[..len]);
+        assert!(!reader.is_connection_reusable());
+    }
+
+    #[tokio::test]
+    async fn no_content_without_length_has_empty_body() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 204 No Content\r\n\r\nHTTP/1.1 200 OK\r\n\r\n");
+        let mut header_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+        assert!(response.is_connection_reusable());
+        assert_eq!(0, response.body().discard().await.unwrap());
+        assert!(!conn.is_exhausted());
+    }
+
     #[tokio::test]
     async fn can_read_to_end_of_connection_to_other_buffer() {
         let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\n\r\nHELLO WORLD");
@@ -676,11 +2661,244 @@ mod tests {
         assert!(conn.is_exhausted());
     }
 
+    #[tokio::test]
+    async fn can_copy_chunked_body_to_writer() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n6\r\n WORLD\r\n0\r\n\r\n",
+        );
+        conn.read_length = 10;
+        let mut header_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+        let mut writer = std::vec::Vec::new();
+        let written = response.body_to_writer(&mut writer).await.unwrap();
+
+        assert_eq!(11, written);
+        assert_eq!(b"HELLO WORLD", writer.as_slice());
+        assert!(conn.is_exhausted());
+    }
+
+    #[tokio::test]
+    async fn can_read_body_to_vec() {
+        for response in [
+            b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD".as_slice(),
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n6\r\n WORLD\r\n0\r\n\r\n",
+        ] {
+            let mut conn = FakeSingleReadConnection::new(response);
+            conn.read_length = 10;
+            let mut header_buf = [0; 200];
+            let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+            let body = response.body_to_vec::<11>().await.unwrap();
+
+            assert_eq!(b"HELLO WORLD", body.as_slice());
+            assert!(conn.is_exhausted());
+        }
+    }
+
+    #[tokio::test]
+    async fn body_to_vec_fails_if_body_is_too_large() {
+        for response in [
+            b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD".as_slice(),
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n6\r\n WORLD\r\n0\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\n\r\nHELLO WORLD",
+        ] {
+            let mut conn = FakeSingleReadConnection::new(response);
+            conn.read_length = 10;
+            let mut header_buf = [0; 200];
+            let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+            let result = response.body_to_vec::<10>().await;
+
+            assert!(matches!(result, Err(Error::BodyTooLarge)));
+        }
+    }
+
+    #[tokio::test]
+    async fn can_read_trailers_after_chunked_body() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: Digest\r\n\r\n5\r\nHELLO\r\n6\r\n WORLD\r\n0\r\nDigest: sha-256=abc\r\nExpires: never\r\n\r\n",
+        );
+        conn.read_length = 10;
+        let mut header_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+        let mut reader = response.body().reader();
+        assert!(reader.trailers().is_none());
+
+        let mut body_buf = [0; 200];
+        let len = reader.read_to_end(&mut body_buf).await.unwrap();
+        assert_eq!(b"HELLO WORLD", &body_buf[..len]);
+
+        let mut trailers = reader.trailers().unwrap();
+        assert_eq!(Some(("Digest", b"sha-256=abc".as_slice())), trailers.next());
+        assert_eq!(Some(("Expires", b"never".as_slice())), trailers.next());
+        assert_eq!(None, trailers.next());
+        assert!(conn.is_exhausted());
+    }
+
+    #[tokio::test]
+    async fn trailers_are_only_kept_when_advertised() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n0\r\nDigest: sha-256=abc\r\n\r\n",
+        );
+        let mut header_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+        let mut reader = response.body().reader();
+        let mut body_buf = [0; 200];
+        let len = reader.read_to_end(&mut body_buf).await.unwrap();
+        assert_eq!(b"HELLO", &body_buf[..len]);
+        assert!(reader.trailers().is_none());
+        assert!(conn.is_exhausted());
+    }
+
+    #[tokio::test]
+    async fn trailers_that_do_not_fit_are_skipped() {
+        let response = [
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: Digest, X-Large\r\n\r\n5\r\nHELLO\r\n0\r\n"
+                .as_slice(),
+            b"X-Large: ",
+            &[b'a'; 200],
+            b"\r\nDigest: sha-256=abc\r\n\r\n",
+        ]
+        .concat();
+        let mut conn = FakeSingleReadConnection::new(response.leak());
+        let mut header_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+        let mut reader = response.body().reader();
+        let mut body_buf = [0; 200];
+        let len = reader.read_to_end(&mut body_buf).await.unwrap();
+        assert_eq!(b"HELLO", &body_buf[..len]);
+
+        let mut trailers = reader.trailers().unwrap();
+        assert_eq!(Some(("Digest", b"sha-256=abc".as_slice())), trailers.next());
+        assert_eq!(None, trailers.next());
+        assert!(conn.is_exhausted());
+    }
+
+    #[cfg(feature = "digest")]
+    #[tokio::test]
+    async fn can_verify_trailer_digest() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: Digest\r\n\r\n5\r\nHELLO\r\n6\r\n WORLD\r\n0\r\nDigest: md5=abc, SHA-256=eH7Hbcr9IMGQjrCTahL5Ht0QWrXNfswrGuIDJkg0Xf8=\r\n\r\n",
+        );
+        conn.read_length = 10;
+        let mut header_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+        let mut writer = std::vec::Vec::new();
+        let written = response.verify_trailer_digest(&mut writer).await.unwrap();
+
+        assert_eq!(11, written);
+        assert_eq!(b"HELLO WORLD", writer.as_slice());
+        assert!(conn.is_exhausted());
+    }
+
+    #[cfg(feature = "digest")]
+    #[tokio::test]
+    async fn trailer_digest_mismatch_fails() {
+        for response in [
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n0\r\nDigest: sha-256=eH7Hbcr9IMGQjrCTahL5Ht0QWrXNfswrGuIDJkg0Xf8=\r\n\r\n".as_slice(),
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n0\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHELLO",
+        ] {
+            let mut conn = FakeSingleReadConnection::new(response);
+            conn.read_length = 10;
+            let mut header_buf = [0; 200];
+            let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+            let mut writer = std::vec::Vec::new();
+            let result = response.verify_trailer_digest(&mut writer).await;
+
+            assert!(matches!(result, Err(Error::IntegrityCheckFailed)));
+        }
+    }
+
+    #[tokio::test]
+    async fn can_read_to_end_with_chunked_trailers() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n0\r\nDigest: sha-256=abc\r\n\r\n",
+        );
+        conn.read_length = 10;
+        let mut header_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+        let body = response.body().read_to_end().await.unwrap();
+
+        assert_eq!(b"HELLO", body);
+        assert!(conn.is_exhausted());
+    }
+
+    #[tokio::test]
+    async fn can_read_body_within_max_body_len() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD");
+        let mut header_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+        let body = response.max_body_len(11).body().read_to_end().await.unwrap();
+
+        assert_eq!(b"HELLO WORLD", body);
+    }
+
+    #[tokio::test]
+    async fn content_length_above_max_body_len_is_rejected() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nHELLO WORLD");
+        let mut header_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+        let mut body_buf = [0; 200];
+        let err = response
+            .max_body_len(10)
+            .body()
+            .reader()
+            .read_to_end(&mut body_buf)
+            .await
+            .expect_err("Failure expected");
+
+        assert!(matches!(err, Error::BodyTooLarge));
+    }
+
+    #[tokio::test]
+    async fn chunked_body_above_max_body_len_is_rejected() {
+        let mut conn = FakeSingleReadConnection::new(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHELLO\r\n6\r\n WORLD\r\n0\r\n\r\n",
+        );
+        conn.read_length = 10;
+        let mut header_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+        let err = response
+            .max_body_len(8)
+            .body()
+            .read_to_end()
+            .await
+            .expect_err("Failure expected");
+
+        assert!(matches!(err, Error::BodyTooLarge));
+    }
+
+    #[tokio::test]
+    async fn body_to_end_of_connection_is_limited_while_reading() {
+        let mut conn = FakeSingleReadConnection::new(b"HTTP/1.1 200 OK\r\n\r\nHELLO WORLD");
+        conn.read_length = 100;
+        let mut header_buf = [0; 200];
+        let response = Response::read(&mut conn, Method::GET, &mut header_buf).await.unwrap();
+
+        let mut reader = response.max_body_len(5).body().reader();
+        let mut body_buf = [0; 200];
+
+        assert_eq!(5, reader.read(&mut body_buf).await.unwrap());
+        assert_eq!(b"HELLO", &body_buf[..5]);
+        assert!(matches!(reader.read(&mut body_buf).await, Err(Error::BodyTooLarge)));
+    }
+
     #[tokio::test]
     async fn chunked_body_reader_can_read_with_large_buffer() {
         let mut raw_body = b"1\r\nX\r\n10\r\nYYYYYYYYYYYYYYYY\r\n0\r\n\r\n".as_slice();
         let mut read_buffer = [0; 128];
-        let mut reader = ChunkedBodyReader::new(BufferingReader::new(&mut read_buffer, 0, &mut raw_body));
+        let mut reader = ChunkedBodyReader::new(BufferingReader::new(&mut read_buffer, 0, &mut raw_body), true);
 
         let mut body = [0; 17];
         reader.read_exact(&mut body).await.unwrap();
@@ -694,7 +2912,7 @@ mod tests {
     async fn chunked_body_reader_can_read_with_tiny_buffer() {
         let mut raw_body = b"1\r\nX\r\n10\r\nYYYYYYYYYYYYYYYY\r\n0\r\n\r\n".as_slice();
         let mut read_buffer = [0; 128];
-        let mut reader = ChunkedBodyReader::new(BufferingReader::new(&mut read_buffer, 0, &mut raw_body));
+        let mut reader = ChunkedBodyReader::new(BufferingReader::new(&mut read_buffer, 0, &mut raw_body), true);
 
         let mut body = heapless::Vec::<u8, 17>::new();
         for _ in 0..17 {
@@ -0,0 +1,141 @@
+//! Signing of requests for APIs that authenticate requests with a signature.
+//!
+//! A [`RequestSigner`] set with [`RequestBuilder::signer`](crate::request::RequestBuilder::signer)
+//! computes the value of the `Authorization` header from the request line and headers.
+//! With the `signing` feature, [`HmacSha256Signer`] provides an HMAC-SHA256 signature.
+use embedded_io::ErrorType;
+use embedded_io_async::Write;
+
+use crate::Error;
+
+/// The maximum length of the `Authorization` header value computed by a [`RequestSigner`].
+pub const MAX_SIGNATURE_LEN: usize = 256;
+
+/// Computes the `Authorization` header value of a request.
+///
+/// The signed bytes are the request line and the header lines, each terminated by `\r\n`, exactly as they are
+/// written to the connection. The `Authorization` header is written after them, so it is not signed itself.
+/// The body is not signed.
+pub trait RequestSigner {
+    /// Start signing a new request.
+    fn begin(&self);
+
+    /// Add the next bytes of the request to the signature.
+    fn update(&self, bytes: &[u8]);
+
+    /// Finish the signature and write the `Authorization` header value into `buf`, which is
+    /// [`MAX_SIGNATURE_LEN`] bytes long.
+    fn finish<'b>(&self, buf: &'b mut [u8]) -> Result<&'b str, Error>;
+}
+
+/// A writer that passes all written bytes to a [`RequestSigner`].
+pub(crate) struct SigningWriter<'a, C> {
+    pub(crate) conn: &'a mut C,
+    pub(crate) signer: &'a dyn RequestSigner,
+}
+
+impl<C> ErrorType for SigningWriter<'_, C>
+where
+    C: Write,
+{
+    type Error = C::Error;
+}
+
+impl<C> Write for SigningWriter<'_, C>
+where
+    C: Write,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let written = self.conn.write(buf).await?;
+        self.signer.update(&buf[..written]);
+        Ok(written)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.conn.flush().await
+    }
+}
+
+/// A [`RequestSigner`] that signs requests using HMAC-SHA256.
+///
+/// The `Authorization` header value is the prefix followed by the signature as lowercase hex,
+/// for example `HMAC-SHA256 Credential=device-1, Signature=` followed by 64 hex digits.
+#[cfg(feature = "signing")]
+pub struct HmacSha256Signer<'a> {
+    prefix: &'a str,
+    keyed: hmac::Hmac<sha2::Sha256>,
+    state: core::cell::RefCell<hmac::Hmac<sha2::Sha256>>,
+}
+
+#[cfg(feature = "signing")]
+impl<'a> HmacSha256Signer<'a> {
+    /// Create a signer using the secret `key`, prepending `prefix` to the signature in the header value.
+    pub fn new(prefix: &'a str, key: &[u8]) -> Self {
+        use hmac::Mac;
+
+        // HMAC accepts keys of any length
+        let keyed = hmac::Hmac::new_from_slice(key).unwrap();
+        Self {
+            prefix,
+            state: core::cell::RefCell::new(keyed.clone()),
+            keyed,
+        }
+    }
+}
+
+#[cfg(feature = "signing")]
+impl RequestSigner for HmacSha256Signer<'_> {
+    fn begin(&self) {
+        *self.state.borrow_mut() = self.keyed.clone();
+    }
+
+    fn update(&self, bytes: &[u8]) {
+        use hmac::Mac;
+        self.state.borrow_mut().update(bytes);
+    }
+
+    fn finish<'b>(&self, buf: &'b mut [u8]) -> Result<&'b str, Error> {
+        use hmac::Mac;
+
+        let signature = self.state.replace(self.keyed.clone()).finalize().into_bytes();
+        let len = self.prefix.len() + 2 * signature.len();
+        let value = buf.get_mut(..len).ok_or(Error::BufferTooSmall)?;
+        value[..self.prefix.len()].copy_from_slice(self.prefix.as_bytes());
+        hex::encode_to_slice(signature, &mut value[self.prefix.len()..]).map_err(|_| Error::Codec)?;
+        Ok(core::str::from_utf8(value)?)
+    }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::*;
+    use crate::request::{Request, RequestBuilder};
+
+    #[tokio::test]
+    async fn hmac_sha256_signer() {
+        let signer = HmacSha256Signer::new("HMAC-SHA256 ", b"secret");
+        for _ in 0..2 {
+            let mut buffer: Vec<u8> = Vec::new();
+            Request::get("/")
+                .host("example.com")
+                .signer(&signer)
+                .build()
+                .write(&mut buffer)
+                .await
+                .unwrap();
+
+            assert_eq!(
+                b"GET / HTTP/1.1\r\nHost: example.com\r\nAuthorization: HMAC-SHA256 6f5558653be96c7119743d18589ef9663ea4980545af13f6046acdfee472d622\r\n\r\n",
+                buffer.as_slice()
+            );
+        }
+    }
+
+    #[test]
+    fn hmac_sha256_signer_buffer_too_small() {
+        let signer = HmacSha256Signer::new("HMAC-SHA256 ", b"secret");
+        signer.begin();
+        let mut buf = [0; 64];
+        assert!(matches!(signer.finish(&mut buf), Err(Error::BufferTooSmall)));
+    }
+}
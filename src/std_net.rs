@@ -0,0 +1,117 @@
+//! [`TcpConnect`] and [`Dns`] implementations using `tokio` and the system resolver.
+//!
+//! These allow using the client on a host with a standard library, for example in tests or examples
+//! that run against a local server. Requires the `std` feature.
+extern crate std;
+
+use embedded_io_adapters::tokio_1::FromTokio;
+use embedded_io_async::{ErrorType, Read, Write};
+use embedded_nal_async::{AddrType, Dns, IpAddr, SocketAddr, TcpConnect};
+use std::io;
+
+use crate::TryBufRead;
+
+/// Connects to servers using [`tokio::net::TcpStream`].
+pub struct TokioTcp;
+
+/// A connection created by [`TokioTcp`].
+pub struct TokioConnection(FromTokio<tokio::net::TcpStream>);
+
+impl TokioConnection {
+    /// Get the underlying stream, for example to set socket options.
+    pub fn inner_mut(&mut self) -> &mut tokio::net::TcpStream {
+        self.0.inner_mut()
+    }
+}
+
+impl TcpConnect for TokioTcp {
+    type Error = io::Error;
+    type Connection<'m> = TokioConnection;
+
+    async fn connect<'m>(&'m self, remote: SocketAddr) -> Result<Self::Connection<'m>, Self::Error> {
+        let ip: std::net::IpAddr = match remote.ip() {
+            IpAddr::V4(ip) => ip.octets().into(),
+            IpAddr::V6(ip) => ip.octets().into(),
+        };
+        let stream = tokio::net::TcpStream::connect((ip, remote.port())).await?;
+        Ok(TokioConnection(FromTokio::new(stream)))
+    }
+}
+
+impl ErrorType for TokioConnection {
+    type Error = io::Error;
+}
+
+impl Read for TokioConnection {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.0.read(buf).await
+    }
+}
+
+impl Write for TokioConnection {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.0.write(buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.0.flush().await
+    }
+}
+
+impl TryBufRead for TokioConnection {}
+
+/// Resolves host names using the system resolver through [`tokio::net::lookup_host`].
+pub struct TokioDns;
+
+impl Dns for TokioDns {
+    type Error = io::Error;
+
+    async fn get_host_by_name(&self, host: &str, addr_type: AddrType) -> Result<IpAddr, Self::Error> {
+        for address in tokio::net::lookup_host((host, 0)).await? {
+            match address.ip() {
+                std::net::IpAddr::V4(ip) if addr_type != AddrType::IPv6 => return Ok(IpAddr::V4(ip.octets().into())),
+                std::net::IpAddr::V6(ip) if addr_type != AddrType::IPv4 => return Ok(IpAddr::V6(ip.octets().into())),
+                _ => {}
+            }
+        }
+        Err(io::ErrorKind::AddrNotAvailable.into())
+    }
+
+    async fn get_host_by_address(&self, _: IpAddr, _: &mut [u8]) -> Result<usize, Self::Error> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::HttpClient;
+    use crate::request::Method;
+    use crate::response::Status;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn can_fetch_from_local_server() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 256];
+            let len = stream.read(&mut request).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHELLO")
+                .await
+                .unwrap();
+            std::string::String::from_utf8(request[..len].to_vec()).unwrap()
+        });
+
+        let url = std::format!("http://localhost:{port}/hello");
+        let mut client = HttpClient::new(&TokioTcp, &TokioDns);
+        let mut rx_buf = [0; 256];
+        let (status, body) = client.fetch(Method::GET, &url, &mut rx_buf).await.unwrap();
+
+        assert_eq!(Status::Ok, status);
+        assert_eq!(b"HELLO", body);
+        assert!(server.await.unwrap().starts_with("GET /hello HTTP/1.1\r\n"));
+    }
+}
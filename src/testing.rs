@@ -0,0 +1,206 @@
+//! In-memory network stack for testing code that uses the [`HttpClient`](crate::client::HttpClient).
+//!
+//! [`MockTcp`] serves canned response bytes instead of connecting to a server, and records the bytes
+//! written by the client so that tests can assert on the requests that were sent.
+//! [`MockDns`] resolves every host name to the loopback address.
+//!
+//! ```ignore
+//! let tcp: MockTcp<1024> = MockTcp::new(b"HTTP/1.1 204 No Content\r\n\r\n");
+//! let mut client = HttpClient::new(&tcp, &MockDns);
+//! let response = client.request(Method::GET, "http://example.com/").await?.send(&mut rx_buf).await?;
+//! assert!(tcp.written().starts_with(b"GET / HTTP/1.1\r\n"));
+//! ```
+use core::cell::RefCell;
+
+use embedded_io::ErrorType;
+use embedded_io_async::{Read, Write};
+use embedded_nal_async::{AddrType, Dns, IpAddr, Ipv4Addr, SocketAddr, TcpConnect};
+
+use crate::{Error, TryBufRead};
+
+/// A [`TcpConnect`] implementation that serves a canned response from memory.
+///
+/// All connections share the same response bytes, so a second connection continues reading
+/// where the previous one stopped. Reading past the end of the response returns end-of-file,
+/// like a server that closed the connection.
+/// Up to `N` bytes written by the client are recorded, writing more fails with [`Error::BufferTooSmall`].
+pub struct MockTcp<'a, const N: usize> {
+    response: &'a [u8],
+    state: RefCell<MockState<N>>,
+}
+
+struct MockState<const N: usize> {
+    read: usize,
+    connections: usize,
+    failing_connections: usize,
+    reset_after: Option<usize>,
+    remote: Option<SocketAddr>,
+    written: heapless::Vec<u8, N>,
+}
+
+impl<'a, const N: usize> MockTcp<'a, N> {
+    /// Create a network stack that serves `response` to the client.
+    pub fn new(response: &'a [u8]) -> Self {
+        Self {
+            response,
+            state: RefCell::new(MockState {
+                read: 0,
+                connections: 0,
+                failing_connections: 0,
+                reset_after: None,
+                remote: None,
+                written: heapless::Vec::new(),
+            }),
+        }
+    }
+
+    /// Make reading from the first `connections` connections fail with [`Error::ConnectionReset`],
+    /// like a server that dropped the connection.
+    pub fn with_failing_connections(self, connections: usize) -> Self {
+        self.state.borrow_mut().failing_connections = connections;
+        self
+    }
+
+    /// Make reading from the first connection fail with [`Error::ConnectionReset`] once `len` bytes of the
+    /// response were read from it, like a server that dropped an idle connection.
+    /// Later connections continue reading the response from there.
+    pub fn with_reset_after(self, len: usize) -> Self {
+        self.state.borrow_mut().reset_after = Some(len);
+        self
+    }
+
+    /// Get the bytes written by the client on all connections.
+    pub fn written(&self) -> heapless::Vec<u8, N> {
+        self.state.borrow().written.clone()
+    }
+
+    /// Get the number of connections established by the client.
+    pub fn connections(&self) -> usize {
+        self.state.borrow().connections
+    }
+
+    /// Get the address of the last connection established by the client.
+    pub fn remote(&self) -> Option<SocketAddr> {
+        self.state.borrow().remote
+    }
+}
+
+impl<'a, const N: usize> TcpConnect for MockTcp<'a, N> {
+    type Error = Error;
+    type Connection<'m>
+        = MockConnection<'m, 'a, N>
+    where
+        Self: 'm;
+
+    async fn connect<'m>(&'m self, remote: SocketAddr) -> Result<Self::Connection<'m>, Self::Error> {
+        let mut state = self.state.borrow_mut();
+        state.connections += 1;
+        state.remote = Some(remote);
+        Ok(MockConnection {
+            tcp: self,
+            failing: state.connections <= state.failing_connections,
+            reset_after: state.reset_after.filter(|_| state.connections == 1),
+        })
+    }
+}
+
+/// A connection established by [`MockTcp`].
+pub struct MockConnection<'m, 'a, const N: usize> {
+    tcp: &'m MockTcp<'a, N>,
+    failing: bool,
+    reset_after: Option<usize>,
+}
+
+impl<const N: usize> ErrorType for MockConnection<'_, '_, N> {
+    type Error = Error;
+}
+
+impl<const N: usize> Read for MockConnection<'_, '_, N> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.failing {
+            return Err(Error::ConnectionReset);
+        }
+
+        let mut state = self.tcp.state.borrow_mut();
+        let end = match self.reset_after {
+            Some(len) if state.read >= len => return Err(Error::ConnectionReset),
+            Some(len) => len.min(self.tcp.response.len()),
+            None => self.tcp.response.len(),
+        };
+        let remaining = &self.tcp.response[state.read..end];
+        let len = buf.len().min(remaining.len());
+        buf[..len].copy_from_slice(&remaining[..len]);
+        state.read += len;
+        Ok(len)
+    }
+}
+
+impl<const N: usize> Write for MockConnection<'_, '_, N> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let mut state = self.tcp.state.borrow_mut();
+        state
+            .written
+            .extend_from_slice(buf)
+            .map_err(|_| Error::BufferTooSmall)?;
+        Ok(buf.len())
+    }
+}
+
+impl<const N: usize> TryBufRead for MockConnection<'_, '_, N> {}
+
+/// A [`Dns`] implementation that resolves every host name to the IPv4 loopback address.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockDns;
+
+impl Dns for MockDns {
+    type Error = Error;
+
+    async fn get_host_by_name(&self, _host: &str, _addr_type: AddrType) -> Result<IpAddr, Self::Error> {
+        Ok(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)))
+    }
+
+    async fn get_host_by_address(&self, _addr: IpAddr, _result: &mut [u8]) -> Result<usize, Self::Error> {
+        Err(Error::Dns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::HttpClient;
+    use crate::request::{Method, RequestBuilder};
+
+    #[tokio::test]
+    async fn client_reads_canned_response() {
+        let tcp: MockTcp<1024> = MockTcp::new(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHELLO");
+        let mut client = HttpClient::new(&tcp, &MockDns).without_user_agent();
+        let mut rx_buf = [0; 256];
+
+        let mut request = client
+            .request(Method::POST, "http://example.com/hello")
+            .await
+            .unwrap()
+            .body(b"PING".as_slice());
+        let response = request.send(&mut rx_buf).await.unwrap();
+        let body = response.body().read_to_end().await.unwrap();
+
+        assert_eq!(b"HELLO", body);
+        assert_eq!(1, tcp.connections());
+        assert_eq!(
+            b"POST /hello HTTP/1.1\r\nHost: example.com\r\nContent-Length: 4\r\n\r\nPING",
+            tcp.written().as_slice()
+        );
+    }
+
+    #[tokio::test]
+    async fn exhausted_response_closes_connection() {
+        let tcp: MockTcp<1024> = MockTcp::new(b"");
+        let mut client = HttpClient::new(&tcp, &MockDns);
+        let mut rx_buf = [0; 256];
+
+        let mut request = client.request(Method::GET, "http://example.com/").await.unwrap();
+        let result = request.send(&mut rx_buf).await;
+
+        assert!(matches!(result, Err(Error::ConnectionAborted)));
+    }
+}
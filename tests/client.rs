@@ -6,7 +6,7 @@ use rand::rngs::OsRng;
 use rand::RngCore;
 use reqwless::client::HttpClient;
 use reqwless::headers::ContentType;
-use reqwless::request::{Method, RequestBuilder};
+use reqwless::request::{Method, Request, RequestBuilder};
 use reqwless::response::Status;
 use std::net::SocketAddr;
 use std::sync::Once;
@@ -90,6 +90,7 @@ async fn test_resource_notls() {
     let mut client = HttpClient::new(&TCP, &LOOPBACK_DNS);
     let mut rx_buf = [0; 4096];
     let mut resource = client.resource(&url).await.unwrap();
+    assert_eq!(addr.port(), resource.peer_addr().port());
     for _ in 0..2 {
         let response = resource
             .post("/")
@@ -159,7 +160,7 @@ async fn test_resource_notls_bufread() {
 #[tokio::test]
 #[cfg(feature = "embedded-tls")]
 async fn test_resource_rustls() {
-    use reqwless::client::{TlsConfig, TlsVerify};
+    use reqwless::client::{TlsConfig, TlsVerify, MIN_TLS_BUFFER_LEN};
 
     setup();
     let addr: SocketAddr = ([127, 0, 0, 1], 0).into();
@@ -199,7 +200,7 @@ async fn test_resource_rustls() {
         }
     });
 
-    let mut tls_read_buf: [u8; 16384] = [0; 16384];
+    let mut tls_read_buf: [u8; MIN_TLS_BUFFER_LEN] = [0; MIN_TLS_BUFFER_LEN];
     let mut tls_write_buf: [u8; 16384] = [0; 16384];
     let url = format!("https://localhost:{}", addr.port());
     let mut client = HttpClient::new_with_tls(
@@ -220,6 +221,78 @@ async fn test_resource_rustls() {
         let body = response.body().read_to_end().await;
         assert_eq!(body.unwrap(), b"PING");
     }
+    resource.conn.close().await.unwrap();
+
+    tx.send(()).unwrap();
+    t.await.unwrap();
+}
+
+#[tokio::test]
+#[cfg(feature = "embedded-tls")]
+async fn test_resource_rustls_with_rng() {
+    use reqwless::client::{TlsConfig, TlsVerify, MIN_TLS_BUFFER_LEN};
+
+    setup();
+    let addr: SocketAddr = ([127, 0, 0, 1], 0).into();
+
+    let test_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let certs = load_certs(&test_dir.join("certs").join("cert.pem"));
+    let privkey = load_private_key(&test_dir.join("certs").join("key.pem"));
+
+    let versions = &[&rustls::version::TLS13];
+    let config = rustls::ServerConfig::builder()
+        .with_cipher_suites(rustls::ALL_CIPHER_SUITES)
+        .with_kx_groups(&rustls::ALL_KX_GROUPS)
+        .with_protocol_versions(versions)
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(certs, privkey)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))
+        .unwrap();
+    let acceptor = TlsAcceptor::from(std::sync::Arc::new(config));
+
+    let listener = TcpListener::bind(&addr).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (tx, rx) = oneshot::channel();
+    let t = tokio::spawn(async move {
+        tokio::select! {
+            _ = async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let stream = acceptor.accept(stream).await.unwrap();
+                Http::new()
+                    .http1_only(true)
+                    .http1_keep_alive(true)
+                    .serve_connection(stream, service_fn(echo))
+                    .await.unwrap();
+        } => {}
+            _ = rx => {}
+        }
+    });
+
+    let mut tls_read_buf: [u8; MIN_TLS_BUFFER_LEN] = [0; MIN_TLS_BUFFER_LEN];
+    let mut tls_write_buf: [u8; 16384] = [0; 16384];
+    let url = format!("https://localhost:{}", addr.port());
+    let mut rng = OsRng;
+    let mut client = HttpClient::new_with_tls(
+        &TCP,
+        &LOOPBACK_DNS,
+        TlsConfig::new_with_rng(&mut rng, &mut tls_read_buf, &mut tls_write_buf, TlsVerify::None),
+    );
+    let mut rx_buf = [0; 4096];
+    let mut resource = client.resource(&url).await.unwrap();
+    for _ in 0..2 {
+        let response = resource
+            .post("/")
+            .body(b"PING".as_slice())
+            .content_type(ContentType::TextPlain)
+            .send(&mut rx_buf)
+            .await
+            .unwrap();
+        let body = response.body().read_to_end().await;
+        assert_eq!(body.unwrap(), b"PING");
+    }
+    resource.conn.close().await.unwrap();
 
     tx.send(()).unwrap();
     t.await.unwrap();
@@ -229,10 +302,10 @@ async fn test_resource_rustls() {
 #[tokio::test]
 #[cfg(feature = "embedded-tls")]
 async fn test_resource_drogue_cloud_sandbox() {
-    use reqwless::client::{TlsConfig, TlsVerify};
+    use reqwless::client::{TlsConfig, TlsVerify, MIN_TLS_BUFFER_LEN};
 
     setup();
-    let mut tls_read_buf: [u8; 16384] = [0; 16384];
+    let mut tls_read_buf: [u8; MIN_TLS_BUFFER_LEN] = [0; MIN_TLS_BUFFER_LEN];
     let mut tls_write_buf: [u8; 16384] = [0; 16384];
     let mut client = HttpClient::new_with_tls(
         &TCP,
@@ -296,6 +369,244 @@ async fn test_request_response_notls_buffered() {
     t.await.unwrap();
 }
 
+#[tokio::test]
+#[cfg(feature = "embedded-tls")]
+async fn test_request_response_notls_with_tls_config() {
+    use reqwless::client::{TlsConfig, TlsVerify};
+
+    setup();
+    let addr = ([127, 0, 0, 1], 0).into();
+
+    let service = make_service_fn(|_| async { Ok::<_, hyper::Error>(service_fn(echo)) });
+
+    let server = Server::bind(&addr).serve(service);
+    let addr = server.local_addr();
+
+    let (tx, rx) = oneshot::channel();
+    let t = tokio::spawn(async move {
+        tokio::select! {
+            _ = server => {}
+            _ = rx => {}
+        }
+    });
+
+    // Plain connections reuse the TLS buffers to buffer both reads and writes
+    let mut tls_read_buf: [u8; 64] = [0; 64];
+    let mut tls_write_buf: [u8; 64] = [0; 64];
+    let url = format!("http://127.0.0.1:{}", addr.port());
+    let mut client = HttpClient::new_with_tls(
+        &TCP,
+        &LOOPBACK_DNS,
+        TlsConfig::new(OsRng.next_u64(), &mut tls_read_buf, &mut tls_write_buf, TlsVerify::None),
+    );
+    let mut rx_buf = [0; 4096];
+    let mut resource = client.resource(&url).await.unwrap();
+    for _ in 0..2 {
+        let response = resource
+            .post("/")
+            .body(b"PING".as_slice())
+            .content_type(ContentType::TextPlain)
+            .send(&mut rx_buf)
+            .await
+            .unwrap();
+        let body = response.body().read_to_end().await;
+        assert_eq!(body.unwrap(), b"PING");
+    }
+
+    tx.send(()).unwrap();
+    t.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_pipelined_requests_notls() {
+    setup();
+    let addr = ([127, 0, 0, 1], 0).into();
+
+    let service = make_service_fn(|_| async { Ok::<_, hyper::Error>(service_fn(echo)) });
+
+    let server = Server::bind(&addr).serve(service);
+    let addr = server.local_addr();
+
+    let (tx, rx) = oneshot::channel();
+    let t = tokio::spawn(async move {
+        tokio::select! {
+            _ = server => {}
+            _ = rx => {}
+        }
+    });
+
+    let url = format!("http://127.0.0.1:{}", addr.port());
+    let mut client = HttpClient::new(&TCP, &LOOPBACK_DNS);
+    let mut handle = client.request(Method::GET, &url).await.unwrap();
+    let requests = [
+        Request::put("/").body(b"PING".as_slice()).build(),
+        Request::put("/").body(b"PONG".as_slice()).build(),
+        Request::put("/").body(b"PANG".as_slice()).build(),
+    ];
+    let mut read_buf = [0; 4096];
+    let mut rx_buf = [0; 4096];
+    let mut pipeline = handle.conn.pipeline(&requests, &mut read_buf).await.unwrap();
+    for expected in [b"PING", b"PONG", b"PANG"] {
+        let response = pipeline.next_response(&mut rx_buf).await.unwrap().unwrap();
+        let body = response.body().read_to_end().await;
+        assert_eq!(body.unwrap(), expected);
+    }
+    assert!(pipeline.next_response(&mut rx_buf).await.unwrap().is_none());
+    assert_eq!(0, pipeline.unanswered());
+
+    tx.send(()).unwrap();
+    t.await.unwrap();
+}
+
+struct DualStackDns;
+
+impl embedded_nal_async::Dns for DualStackDns {
+    type Error = TestError;
+
+    async fn get_host_by_name(
+        &self,
+        _: &str,
+        addr_type: embedded_nal_async::AddrType,
+    ) -> Result<embedded_nal_async::IpAddr, Self::Error> {
+        // Nothing listens on the IPv6 loopback address, so connecting to it is expected to fail
+        match addr_type {
+            embedded_nal_async::AddrType::IPv4 => Ok(embedded_nal_async::Ipv4Addr::LOCALHOST.into()),
+            _ => Ok(embedded_nal_async::Ipv6Addr::LOCALHOST.into()),
+        }
+    }
+
+    async fn get_host_by_address(&self, _: embedded_nal_async::IpAddr, _: &mut [u8]) -> Result<usize, Self::Error> {
+        Err(TestError)
+    }
+}
+
+#[tokio::test]
+async fn test_request_falls_back_to_other_address_type() {
+    setup();
+    let addr = ([127, 0, 0, 1], 0).into();
+
+    let service = make_service_fn(|_| async { Ok::<_, hyper::Error>(service_fn(echo)) });
+
+    let server = Server::bind(&addr).serve(service);
+    let addr = server.local_addr();
+
+    let (tx, rx) = oneshot::channel();
+    let t = tokio::spawn(async move {
+        tokio::select! {
+            _ = server => {}
+            _ = rx => {}
+        }
+    });
+
+    let url = format!("http://localhost:{}", addr.port());
+    let mut client = HttpClient::new(&TCP, &DualStackDns);
+    let mut rx_buf = [0; 4096];
+    let mut request = client
+        .request(Method::POST, &url)
+        .await
+        .unwrap()
+        .body(b"PING".as_slice())
+        .content_type(ContentType::TextPlain);
+    assert!(request.peer_addr().is_ipv4());
+    let response = request.send(&mut rx_buf).await.unwrap();
+    let body = response.body().read_to_end().await;
+    assert_eq!(body.unwrap(), b"PING");
+
+    tx.send(()).unwrap();
+    t.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_request_connection_refused() {
+    setup();
+    // Bind to get a free port, and close the listener so that nothing listens on it
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let url = format!("http://127.0.0.1:{}", port);
+    let mut client = HttpClient::new(&TCP, &LOOPBACK_DNS);
+    let result = client.request(Method::GET, &url).await;
+    assert!(matches!(result, Err(reqwless::Error::ConnectionRefused)));
+}
+
+#[tokio::test]
+async fn test_request_send_cloneable() {
+    setup();
+    let addr = ([127, 0, 0, 1], 0).into();
+
+    let service = make_service_fn(|_| async { Ok::<_, hyper::Error>(service_fn(echo)) });
+
+    let server = Server::bind(&addr).serve(service);
+    let addr = server.local_addr();
+
+    let (tx, rx) = oneshot::channel();
+    let t = tokio::spawn(async move {
+        tokio::select! {
+            _ = server => {}
+            _ = rx => {}
+        }
+    });
+
+    let url = format!("http://127.0.0.1:{}", addr.port());
+    let mut client = HttpClient::new(&TCP, &LOOPBACK_DNS);
+    let mut rx_buf = [0; 4096];
+    let mut request = client
+        .request(Method::POST, &url)
+        .await
+        .unwrap()
+        .body(b"PING".as_slice())
+        .content_type(ContentType::TextPlain);
+    for _ in 0..2 {
+        let response = request.send_cloneable(&mut rx_buf).await.unwrap();
+        let body = response.body().read_to_end().await;
+        assert_eq!(body.unwrap(), b"PING");
+    }
+    drop(request);
+
+    tx.send(()).unwrap();
+    t.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_request_no_dns() {
+    setup();
+    let addr = ([127, 0, 0, 1], 0).into();
+
+    let service = make_service_fn(|_| async { Ok::<_, hyper::Error>(service_fn(echo)) });
+
+    let server = Server::bind(&addr).serve(service);
+    let addr = server.local_addr();
+
+    let (tx, rx) = oneshot::channel();
+    let t = tokio::spawn(async move {
+        tokio::select! {
+            _ = server => {}
+            _ = rx => {}
+        }
+    });
+
+    let url = format!("http://127.0.0.1:{}", addr.port());
+    let mut client = HttpClient::new_no_dns(&TCP);
+    let mut rx_buf = [0; 4096];
+    let mut request = client
+        .request(Method::POST, &url)
+        .await
+        .unwrap()
+        .body(b"PING".as_slice())
+        .content_type(ContentType::TextPlain);
+    let response = request.send(&mut rx_buf).await.unwrap();
+    let body = response.body().read_to_end().await;
+    assert_eq!(body.unwrap(), b"PING");
+    drop(request);
+
+    let result = client.request(Method::GET, "http://localhost/").await;
+    assert!(matches!(result, Err(reqwless::Error::Dns)));
+
+    tx.send(()).unwrap();
+    t.await.unwrap();
+}
+
 fn load_certs(filename: &std::path::PathBuf) -> Vec<rustls::Certificate> {
     let certfile = std::fs::File::open(filename).expect("cannot open certificate file");
     let mut reader = std::io::BufReader::new(certfile);